@@ -0,0 +1,288 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use crate::interval::{intersect_all, Interval};
+use crate::virtual_map::{Range, VirtualMap};
+
+pub const SEED: &str = "seed";
+pub const LOCATION: &str = "location";
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct Almanac {
+    pub seeds: Vec<u64>,
+    pub stages: Vec<Stage>,
+}
+
+// one `source-to-destination map:` section
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Stage {
+    pub source: String,
+    pub destination: String,
+    pub map: VirtualMap,
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}-to-{} map:", self.source, self.destination)?;
+        write!(f, "{}", self.map)
+    }
+}
+
+// writes the puzzle text format back, so that parsing the output gives an
+// equal almanac
+impl Display for Almanac {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "seeds:")?;
+        for seed in &self.seeds {
+            write!(f, " {}", seed)?;
+        }
+        writeln!(f)?;
+        for stage in &self.stages {
+            writeln!(f)?;
+            write!(f, "{}", stage)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum AlmanacError {
+    MissingLink(String),
+    AmbiguousLink(String),
+    Cycle(String),
+    SeedRangeOverflow { start: u64, length: u64 },
+    IncompletePair(usize),
+}
+
+impl Display for AlmanacError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AlmanacError::MissingLink(category) => write!(f, "no map leads out of category {}", category),
+            AlmanacError::AmbiguousLink(category) => write!(f, "several maps lead out of category {}", category),
+            AlmanacError::Cycle(category) => write!(f, "maps loop back to category {}", category),
+            AlmanacError::SeedRangeOverflow { start, length } => write!(f, "seed range of length {} starting at {} goes past {}", length, start, u64::MAX),
+            AlmanacError::IncompletePair(count) => write!(f, "{} seed values can not be read as start and length pairs", count),
+        }
+    }
+}
+
+impl std::error::Error for AlmanacError {}
+
+// how the seeds line is read: single seeds for part 1, start and length
+// pairs for part 2
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum SeedSpec {
+    List(Vec<u64>),
+    Ranges(Vec<Interval>),
+}
+
+impl SeedSpec {
+    pub fn list(seeds: &[u64]) -> Result<Self, AlmanacError> {
+        Ok(SeedSpec::List(seeds.to_vec()))
+    }
+
+    pub fn pairs(seeds: &[u64]) -> Result<Self, AlmanacError> {
+        if !seeds.len().is_multiple_of(2) {
+            return Err(AlmanacError::IncompletePair(seeds.len()));
+        }
+        let ranges = seeds
+            .chunks(2)
+            .map(|pair| Interval::with_len(pair[0], pair[1]).ok_or(AlmanacError::SeedRangeOverflow { start: pair[0], length: pair[1] }))
+            .collect::<Result<_, _>>()?;
+        Ok(SeedSpec::Ranges(ranges))
+    }
+
+    // the seeds as intervals, one per seed for a list. No half-open interval
+    // holds u64::MAX, so a list leaves it out
+    pub fn intervals(&self) -> Vec<Interval> {
+        match self {
+            SeedSpec::List(seeds) => seeds.iter().filter_map(|s| Interval::with_len(*s, 1)).collect(),
+            SeedSpec::Ranges(ranges) => ranges.clone(),
+        }
+    }
+
+    pub fn contains(&self, seed: u64) -> bool {
+        match self {
+            SeedSpec::List(seeds) => seeds.contains(&seed),
+            SeedSpec::Ranges(ranges) => ranges.iter().any(|r| r.contains(seed)),
+        }
+    }
+}
+
+impl Almanac {
+    // follows the map headers from one category to another
+    pub fn chain(&self, from: &str, to: &str) -> Result<Vec<&Stage>, AlmanacError> {
+        let mut chain = Vec::new();
+        let mut visited = HashSet::new();
+        let mut current = from;
+        while current != to {
+            if !visited.insert(current) {
+                return Err(AlmanacError::Cycle(current.to_string()));
+            }
+            let mut outgoing = self.stages.iter().filter(|s| s.source == current);
+            let stage = outgoing.next().ok_or_else(|| AlmanacError::MissingLink(current.to_string()))?;
+            if outgoing.next().is_some() {
+                return Err(AlmanacError::AmbiguousLink(current.to_string()));
+            }
+            chain.push(stage);
+            current = &stage.destination;
+        }
+        Ok(chain)
+    }
+
+    // converts a value between any two linked categories, walking the maps
+    // backwards when `to` comes before `from`
+    pub fn convert(&self, from: &str, to: &str, value: u64) -> Result<u64, AlmanacError> {
+        match self.chain(from, to) {
+            Ok(chain) => Ok(chain.iter().fold(value, |v, s| s.map.get(&v))),
+            Err(e @ AlmanacError::MissingLink(_)) => match self.chain(to, from) {
+                Ok(chain) => Ok(chain.iter().rev().fold(value, |v, s| s.map.rget(&v))),
+                Err(_) => Err(e),
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    // values of `from` that end up in the targets once converted to `to`
+    pub fn preimage(&self, from: &str, to: &str, targets: &[Interval]) -> Result<Vec<Interval>, AlmanacError> {
+        let chain = self.chain(from, to)?;
+        Ok(chain.iter().rev().fold(targets.to_vec(), |t, s| s.map.preimage(&t)))
+    }
+
+    // seed intervals whose location falls in the given range, optionally
+    // restricted to the given seeds
+    pub fn seeds_for_locations(&self, locations: Interval, clip_to: Option<&SeedSpec>) -> Result<Vec<Interval>, AlmanacError> {
+        let seeds = self.preimage(SEED, LOCATION, &[locations])?;
+        if let Some(spec) = clip_to {
+            return Ok(intersect_all(&seeds, &spec.intervals()));
+        }
+        Ok(seeds)
+    }
+
+    // smallest domain starting at 0 that holds every range of every stage
+    pub fn domain(&self) -> Interval {
+        let end = self
+            .stages
+            .iter()
+            .flat_map(|s| s.map.ranges())
+            .map(|r| r.source_range().end.max(r.destination_range().end))
+            .max()
+            .unwrap_or(0);
+        Interval::new(0, end)
+    }
+
+    pub fn instructions(&self) -> Result<Vec<Instruction>, AlmanacError> {
+        let chain = self.chain(SEED, LOCATION)?;
+        Ok(self.seeds.iter().map(|seed| trace(*seed, &chain)).collect())
+    }
+
+    pub fn instruction(&self, seed: u64) -> Result<Instruction, AlmanacError> {
+        Ok(trace(seed, &self.chain(SEED, LOCATION)?))
+    }
+
+    pub fn get_location(&self, seed: &u64) -> Result<u64, AlmanacError> {
+        self.convert(SEED, LOCATION, *seed)
+    }
+
+    // lowest of the seeds reaching the location. Walking back with rget
+    // would only find one preimage, missing seeds that pass through a map
+    // unchanged onto a range's destination
+    pub fn seed_for_loc(&self, loc: u64, seeds: &SeedSpec) -> Result<Option<u64>, AlmanacError> {
+        let reaching = self.seeds_for_locations(Interval::new(loc, loc + 1), Some(seeds))?;
+        Ok(reaching.first().map(|i| i.start))
+    }
+
+    // folds the maps into one map going straight from seed to location
+    pub fn seed_location_map(&self) -> Result<VirtualMap, AlmanacError> {
+        let chain = self.chain(SEED, LOCATION)?;
+        Ok(chain.iter().fold(VirtualMap::new(), |acc, s| acc.compose(&s.map)))
+    }
+
+    // pushes seed ranges through the seed to location map as intervals,
+    // splitting them at range boundaries, and returns the lowest location.
+    // Single seeds are traced one by one, u64::MAX included
+    pub fn lowest_location(&self, seeds: &SeedSpec) -> Result<Option<u64>, AlmanacError> {
+        if let SeedSpec::List(list) = seeds {
+            let chain = self.chain(SEED, LOCATION)?;
+            return Ok(list.iter().map(|s| trace(*s, &chain).location()).min());
+        }
+        Ok(self.seed_location_map()?
+            .get_ranges(&seeds.intervals())
+            .iter()
+            .map(|r| r.start)
+            .min())
+    }
+
+    // scans locations upward until one maps back to one of the seeds
+    pub fn lowest_location_bruteforce(&self, seeds: &SeedSpec) -> Result<Option<u64>, AlmanacError> {
+        if seeds.intervals().iter().all(Interval::is_empty) {
+            return Ok(None);
+        }
+        let mut i = 0;
+        loop {
+            if self.seed_for_loc(i, seeds)?.is_some() {
+                return Ok(Some(i))
+            }
+            i += 1;
+        }
+    }
+
+    // normalizes every map and puts the stages in seed to location order
+    // when they form a chain, so that almanacs mapping the same way compare
+    // equal however their input was laid out
+    pub fn simplify(&mut self) {
+        for stage in &mut self.stages {
+            stage.map.normalize();
+        }
+        let order: Vec<String> = self
+            .chain(SEED, LOCATION)
+            .map(|chain| chain.iter().map(|s| s.source.clone()).collect())
+            .unwrap_or_default();
+        self.stages.sort_by_key(|s| order.iter().position(|o| *o == s.source).unwrap_or(order.len()));
+    }
+
+    pub fn part1(&self) -> Result<Option<u64>, AlmanacError> {
+        self.lowest_location(&SeedSpec::list(&self.seeds)?)
+    }
+
+    pub fn part2(&self) -> Result<Option<u64>, AlmanacError> {
+        self.lowest_location(&SeedSpec::pairs(&self.seeds)?)
+    }
+}
+
+fn trace(seed: u64, chain: &[&Stage]) -> Instruction {
+    let mut value = seed;
+    let mut steps = Vec::new();
+    for stage in chain {
+        let range = stage.map.find(&value).cloned();
+        value = range.as_ref().map_or(value, |r| r.map(value));
+        steps.push(Step {
+            category: stage.destination.clone(),
+            value,
+            range,
+        });
+    }
+    Instruction {
+        seed,
+        steps,
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct Instruction {
+    pub seed: u64,
+    pub steps: Vec<Step>,
+}
+
+impl Instruction {
+    pub fn location(&self) -> u64 {
+        self.steps.last().map_or(self.seed, |s| s.value)
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct Step {
+    pub category: String,
+    pub value: u64,
+    // the range that produced the value, None when it fell through as is
+    pub range: Option<Range>,
+}
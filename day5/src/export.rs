@@ -0,0 +1,179 @@
+use std::fmt::{Display, Formatter};
+use std::io::{self, Write};
+use std::str::FromStr;
+use crate::almanac::{Instruction, Step};
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Format {
+    Csv,
+    Json,
+    Table,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct UnknownFormat(pub String);
+
+impl Display for UnknownFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown export format `{}`, expected csv, json or table", self.0)
+    }
+}
+
+impl std::error::Error for UnknownFormat {}
+
+impl FromStr for Format {
+    type Err = UnknownFormat;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            "table" => Ok(Format::Table),
+            _ => Err(UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+// writes every seed with the value and matched range of each stage
+pub fn write_lineage(out: &mut impl Write, instructions: &[Instruction], format: Format) -> io::Result<()> {
+    match format {
+        Format::Csv => write_csv(out, instructions),
+        Format::Json => write_json(out, instructions),
+        Format::Table => write_table(out, instructions),
+    }
+}
+
+fn range_label(step: &Step) -> String {
+    step.range.as_ref().map_or("identity".to_string(), |r| r.to_string())
+}
+
+// header and cells of the lineage, one row per seed
+fn rows(instructions: &[Instruction]) -> Vec<Vec<String>> {
+    let mut header = vec!["seed".to_string()];
+    if let Some(first) = instructions.first() {
+        for step in &first.steps {
+            header.push(step.category.clone());
+            header.push(format!("{} range", step.category));
+        }
+    }
+
+    let mut rows = vec![header];
+    for instruction in instructions {
+        let mut row = vec![instruction.seed.to_string()];
+        for step in &instruction.steps {
+            row.push(step.value.to_string());
+            row.push(range_label(step));
+        }
+        rows.push(row);
+    }
+    rows
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn write_csv(out: &mut impl Write, instructions: &[Instruction]) -> io::Result<()> {
+    for row in rows(instructions) {
+        let fields: Vec<String> = row.iter().map(|f| csv_field(f)).collect();
+        writeln!(out, "{}", fields.join(","))?;
+    }
+    Ok(())
+}
+
+fn json_string(s: &str) -> String {
+    let mut escaped = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+fn write_json(out: &mut impl Write, instructions: &[Instruction]) -> io::Result<()> {
+    writeln!(out, "[")?;
+    for (i, instruction) in instructions.iter().enumerate() {
+        writeln!(out, "  {{")?;
+        writeln!(out, "    \"seed\": {},", instruction.seed)?;
+        writeln!(out, "    \"steps\": [")?;
+        for (j, step) in instruction.steps.iter().enumerate() {
+            let range = match &step.range {
+                Some(r) => format!(
+                    "{{\"source\": [{}, {}], \"destination\": [{}, {}]}}",
+                    r.source_range().start, r.source_range().end, r.destination_range().start, r.destination_range().end,
+                ),
+                None => "null".to_string(),
+            };
+            let separator = if j + 1 < instruction.steps.len() { "," } else { "" };
+            writeln!(
+                out,
+                "      {{\"category\": {}, \"value\": {}, \"range\": {}}}{}",
+                json_string(&step.category), step.value, range, separator,
+            )?;
+        }
+        writeln!(out, "    ]")?;
+        let separator = if i + 1 < instructions.len() { "," } else { "" };
+        writeln!(out, "  }}{}", separator)?;
+    }
+    writeln!(out, "]")
+}
+
+fn write_table(out: &mut impl Write, instructions: &[Instruction]) -> io::Result<()> {
+    let rows = rows(instructions);
+    let mut widths = vec![0; rows[0].len()];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    for row in &rows {
+        let cells: Vec<String> = row.iter().zip(&widths).map(|(c, w)| format!("{:<w$}", c, w = w)).collect();
+        writeln!(out, "{}", cells.join("  ").trim_end())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+fn export(format: Format) -> String {
+    let almanac = crate::parser::parse("./test-input.txt").unwrap();
+    let mut out = Vec::new();
+    write_lineage(&mut out, &almanac.instructions().unwrap()[..2], format).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn test_export_csv() {
+    let csv = export(Format::Csv);
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], "seed,soil,soil range,fertilizer,fertilizer range,water,water range,light,light range,temperature,temperature range,humidity,humidity range,location,location range");
+    assert_eq!(lines[1], "79,81,50..98 -> 52..100,81,identity,81,identity,74,25..95 -> 18..88,78,64..77 -> 68..81,78,identity,82,56..93 -> 60..97");
+}
+
+#[test]
+fn test_export_json() {
+    let json = export(Format::Json);
+    assert!(json.starts_with("[\n  {\n    \"seed\": 79,\n    \"steps\": [\n      {\"category\": \"soil\", \"value\": 81, \"range\": {\"source\": [50, 98], \"destination\": [52, 100]}},\n      {\"category\": \"fertilizer\", \"value\": 81, \"range\": null},\n"));
+    assert!(json.ends_with("\"range\": null}\n    ]\n  }\n]\n"));
+    assert_eq!(json_string("a\"b\\c\n"), "\"a\\\"b\\\\c\\u000a\"");
+}
+
+#[test]
+fn test_export_table() {
+    let table = export(Format::Table);
+    let lines: Vec<&str> = table.lines().collect();
+    assert!(lines[0].starts_with("seed  soil  soil range         fertilizer  fertilizer range  water"));
+    assert!(lines[1].starts_with("79    81    50..98 -> 52..100  81          identity          81"));
+    assert!(lines[2].starts_with("14    14    identity           53          0..15 -> 39..54   49"));
+    assert_eq!("csv".parse(), Ok(Format::Csv));
+    assert_eq!("xml".parse::<Format>(), Err(UnknownFormat("xml".to_string())));
+}
@@ -0,0 +1,198 @@
+use crate::almanac::{Almanac, Stage, LOCATION, SEED};
+use crate::interval::Interval;
+use crate::virtual_map::VirtualMap;
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Settings {
+    // number of maps between seed and location
+    pub stages: usize,
+    pub ranges_per_map: usize,
+    // every value stays below this
+    pub magnitude: u64,
+    pub seed_pairs: usize,
+    // chance for a slot of the domain to be left to the identity fallback
+    pub gap_density: f64,
+    // chance for a run of ranges to be moved elsewhere, so that it collides
+    // with values passing through unchanged
+    pub overlap_density: f64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            stages: 7,
+            ranges_per_map: 10,
+            magnitude: 1 << 32,
+            seed_pairs: 10,
+            gap_density: 0.2,
+            overlap_density: 0.0,
+        }
+    }
+}
+
+// splitmix64, small and stable across releases so that a seed always gives
+// back the same almanac
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // uniform in 0..n
+    fn below(&mut self, n: u64) -> u64 {
+        ((self.next() as u128 * n as u128) >> 64) as u64
+    }
+
+    fn chance(&mut self, p: f64) -> bool {
+        ((self.next() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i as u64 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+fn category(i: usize, stages: usize) -> String {
+    match i {
+        0 => SEED.to_string(),
+        i if i == stages => LOCATION.to_string(),
+        i => format!("category{}", i),
+    }
+}
+
+// cuts 0..magnitude into at most `count` slots
+fn slots(rng: &mut Rng, count: usize, magnitude: u64) -> Vec<Interval> {
+    let mut cuts: Vec<u64> = (1..count).map(|_| 1 + rng.below(magnitude.max(2) - 1)).collect();
+    cuts.push(0);
+    cuts.push(magnitude);
+    cuts.sort();
+    cuts.dedup();
+    cuts.windows(2).map(|w| Interval::new(w[0], w[1])).collect()
+}
+
+// the mapped slots come in runs of neighbours; shuffling the slots of a run
+// and laying them out again from the start of the run keeps the map a
+// bijection over the domain
+fn map(rng: &mut Rng, settings: &Settings) -> VirtualMap {
+    let mut runs: Vec<Vec<Interval>> = vec![vec![]];
+    for slot in slots(rng, settings.ranges_per_map, settings.magnitude) {
+        if rng.chance(settings.gap_density) {
+            runs.push(vec![]);
+        } else {
+            runs.last_mut().unwrap().push(slot);
+        }
+    }
+    runs.retain(|run| !run.is_empty());
+
+    let mut placed: Vec<(Interval, u64)> = Vec::new();
+    let mut moved = Vec::new();
+    for mut run in runs {
+        let region = Interval::new(run[0].start, run[run.len() - 1].end);
+        rng.shuffle(&mut run);
+        if rng.chance(settings.overlap_density) {
+            moved.push((region, run));
+            continue;
+        }
+        let mut destination = region.start;
+        for slot in run {
+            placed.push((slot, destination));
+            destination += slot.len();
+        }
+    }
+
+    // a moved run keeps the first start that neither leaves the domain nor
+    // lands on the destinations already placed, and is dropped otherwise
+    for (region, run) in moved {
+        for _ in 0..8 {
+            let start = rng.below(settings.magnitude - region.len() + 1);
+            let target = Interval::new(start, start + region.len());
+            if placed.iter().all(|(s, d)| !target.overlaps(&Interval::new(*d, d + s.len()))) {
+                let mut destination = start;
+                for slot in &run {
+                    placed.push((*slot, destination));
+                    destination += slot.len();
+                }
+                break;
+            }
+        }
+    }
+
+    let mut m = VirtualMap::new();
+    for (source, destination) in placed {
+        m.insert(source.start, source.len(), destination)
+            .expect("generated ranges never overlap");
+    }
+    m
+}
+
+pub fn generate(seed: u64, settings: &Settings) -> Almanac {
+    let mut rng = Rng(seed);
+
+    let mut seeds = Vec::new();
+    for _ in 0..settings.seed_pairs {
+        let start = rng.below(settings.magnitude);
+        seeds.push(start);
+        seeds.push(1 + rng.below(settings.magnitude - start));
+    }
+
+    let stages = (0..settings.stages)
+        .map(|i| Stage {
+            source: category(i, settings.stages),
+            destination: category(i + 1, settings.stages),
+            map: map(&mut rng, settings),
+        })
+        .collect();
+
+    Almanac {
+        seeds,
+        stages,
+    }
+}
+
+#[test]
+fn test_generate() {
+    let settings = Settings {
+        magnitude: 10_000,
+        ..Settings::default()
+    };
+
+    let almanac = generate(42, &settings);
+    assert_eq!(almanac, generate(42, &settings));
+    assert_ne!(almanac, generate(43, &settings));
+    assert_eq!(almanac.stages.len(), 7);
+    assert_eq!(almanac.seeds.len(), 20);
+    assert_eq!(almanac.chain(SEED, LOCATION).unwrap().len(), 7);
+    assert!(crate::almanac::SeedSpec::pairs(&almanac.seeds).unwrap().intervals().iter().all(|r| !r.is_empty() && r.end <= 10_000));
+
+    let domain = Interval::new(0, 10_000);
+    for stage in &almanac.stages {
+        assert!(stage.map.ranges().len() <= 10);
+        assert!(stage.map.coverage(domain).bijective);
+    }
+
+    assert_eq!(crate::parser::parse_str(&almanac.to_string()).unwrap(), almanac);
+}
+
+#[test]
+fn test_generate_overlaps() {
+    let settings = Settings {
+        magnitude: 1_000_000,
+        ranges_per_map: 40,
+        gap_density: 0.5,
+        overlap_density: 1.0,
+        ..Settings::default()
+    };
+
+    let almanac = generate(7, &settings);
+    let domain = Interval::new(0, 1_000_000);
+    assert!(almanac.stages.iter().any(|s| !s.map.coverage(domain).bijective));
+    assert_eq!(crate::parser::parse_str(&almanac.to_string()).unwrap(), almanac);
+}
@@ -0,0 +1,138 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use crate::almanac::{Almanac, AlmanacError, LOCATION, SEED};
+use crate::interval::Interval;
+
+// cuts the domain at every boundary, in order
+fn atoms(domain: Interval, cuts: &BTreeSet<u64>) -> Vec<Interval> {
+    let mut points: Vec<u64> = cuts.range(domain.start..domain.end).copied().collect();
+    points.push(domain.end);
+    points.windows(2).map(|w| Interval::new(w[0], w[1])).filter(|i| !i.is_empty()).collect()
+}
+
+fn touches(set: &[Interval], atom: &Interval) -> bool {
+    set.iter().any(|i| i.overlaps(atom))
+}
+
+// renders the seed to location stages as a graphviz digraph, one column per
+// category. Each column is cut wherever a range of the next stage starts or
+// ends, or wherever the previous stage split its values, so every node maps
+// as a whole and the arrows show how the intervals fragment on their way to
+// location. The values reached from `highlight` are drawn in red.
+pub fn dot(almanac: &Almanac, highlight: Option<Interval>) -> Result<String, AlmanacError> {
+    let chain = almanac.chain(SEED, LOCATION)?;
+    let domain = almanac.domain();
+
+    let mut columns = vec![];
+    let mut cuts = BTreeSet::from([domain.start]);
+    let mut highlighted = highlight.into_iter().collect::<Vec<_>>();
+    for stage in &chain {
+        for r in stage.map.ranges() {
+            cuts.insert(r.source_range().start);
+            cuts.insert(r.source_range().end);
+        }
+        let column = atoms(domain, &cuts);
+        cuts = column
+            .iter()
+            .flat_map(|a| stage.map.get_ranges(&[*a]))
+            .flat_map(|i| [i.start, i.end])
+            .collect();
+        cuts.insert(domain.start);
+        let next = stage.map.get_ranges(&highlighted);
+        columns.push((stage.source.as_str(), column, highlighted));
+        highlighted = next;
+    }
+    columns.push((LOCATION, atoms(domain, &cuts), highlighted));
+
+    let mut out = String::new();
+    writeln!(out, "digraph almanac {{").unwrap();
+    writeln!(out, "  rankdir=LR;").unwrap();
+    writeln!(out, "  node [shape=box];").unwrap();
+    for (c, (category, column, highlighted)) in columns.iter().enumerate() {
+        writeln!(out, "  subgraph cluster_{} {{", c).unwrap();
+        writeln!(out, "    label=\"{}\";", category).unwrap();
+        writeln!(out, "    rank=same;").unwrap();
+        for (i, atom) in column.iter().enumerate() {
+            let style = if touches(highlighted, atom) { ", color=red" } else { "" };
+            writeln!(out, "    c{}_{} [label=\"{}\"{}];", c, i, atom, style).unwrap();
+        }
+        writeln!(out, "  }}").unwrap();
+    }
+
+    for (c, stage) in chain.iter().enumerate() {
+        let (_, column, highlighted) = &columns[c];
+        let next = &columns[c + 1].1;
+        for (i, atom) in column.iter().enumerate() {
+            let (piece, range) = stage.map.pieces(&[*atom])[0];
+            let image = range.map_or(piece, |r| r.map_interval(piece));
+            let label = match range {
+                Some(r) if r.destination_range().start >= r.source_range().start => {
+                    format!("label=\"+{}\"", r.destination_range().start - r.source_range().start)
+                }
+                Some(r) => format!("label=\"-{}\"", r.source_range().start - r.destination_range().start),
+                None => "style=dashed".to_string(),
+            };
+            let reached: Vec<Interval> = highlighted
+                .iter()
+                .filter_map(|h| h.intersection(atom))
+                .map(|h| range.map_or(h, |r| r.map_interval(h)))
+                .collect();
+            let first = next.partition_point(|n| n.end <= image.start);
+            for (j, target) in next.iter().enumerate().skip(first).take_while(|(_, n)| n.start < image.end) {
+                let style = if touches(&reached, target) { ", color=red" } else { "" };
+                writeln!(out, "  c{}_{} -> c{}_{} [{}{}];", c, i, c + 1, j, label, style).unwrap();
+            }
+        }
+    }
+    writeln!(out, "}}").unwrap();
+    Ok(out)
+}
+
+#[cfg(test)]
+fn edges(dot: &str) -> Vec<&str> {
+    dot.lines().filter(|l| l.contains("->")).map(str::trim).collect()
+}
+
+#[test]
+fn test_dot_columns() {
+    let almanac = crate::parser::parse_str("seeds: 0 10\n\nseed-to-soil map:\n5 0 2\n\nsoil-to-location map:\n0 6 1\n").unwrap();
+    let graph = dot(&almanac, None).unwrap();
+
+    // seed is cut at 2 by its range, soil at 5 where that range lands and at
+    // 6 by the next range
+    assert!(graph.contains("label=\"seed\";\n    rank=same;\n    c0_0 [label=\"0..2\"];\n    c0_1 [label=\"2..7\"];\n  }"));
+    assert!(graph.contains("c1_0 [label=\"0..2\"];\n    c1_1 [label=\"2..5\"];\n    c1_2 [label=\"5..6\"];\n    c1_3 [label=\"6..7\"];\n  }"));
+    assert!(graph.contains("label=\"location\";"));
+    assert_eq!(
+        edges(&graph),
+        vec![
+            "c0_0 -> c1_2 [label=\"+5\"];",
+            "c0_0 -> c1_3 [label=\"+5\"];",
+            "c0_1 -> c1_1 [style=dashed];",
+            "c0_1 -> c1_2 [style=dashed];",
+            "c0_1 -> c1_3 [style=dashed];",
+            "c1_0 -> c2_0 [style=dashed];",
+            "c1_0 -> c2_1 [style=dashed];",
+            "c1_1 -> c2_2 [style=dashed];",
+            "c1_2 -> c2_3 [style=dashed];",
+            "c1_3 -> c2_0 [label=\"-6\"];",
+        ],
+    );
+}
+
+#[test]
+fn test_dot_highlight() {
+    let almanac = crate::parser::parse("./test-input.txt").unwrap();
+    assert!(!dot(&almanac, None).unwrap().contains("red"));
+
+    // seed 79 goes through 81, 74, 78 and ends up at 82
+    let graph = dot(&almanac, Some(Interval::new(79, 80))).unwrap();
+    let red: Vec<&str> = graph.lines().filter(|l| l.contains("red") && !l.contains("->")).collect();
+    assert_eq!(red.len(), 8);
+    for (line, value) in red.iter().zip([79, 81, 81, 81, 74, 78, 78, 82]) {
+        let label = line.split('"').nth(1).unwrap();
+        let (start, end) = label.split_once("..").unwrap();
+        assert!(start.parse::<u64>().unwrap() <= value && value < end.parse::<u64>().unwrap(), "{} misses {}", line, value);
+    }
+    assert_eq!(edges(&graph).iter().filter(|e| e.contains("red")).count(), 7);
+}
@@ -0,0 +1,113 @@
+use std::fmt::{Display, Formatter};
+
+// half-open interval of values, start included and end excluded, as the
+// puzzle defines its ranges: `start..start+length`
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash, PartialOrd, Ord)]
+pub struct Interval {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl Interval {
+    pub fn new(start: u64, end: u64) -> Self {
+        assert!(start <= end, "interval start {} is past its end {}", start, end);
+        Self { start, end }
+    }
+
+    // None when the end would not fit in a u64
+    pub fn with_len(start: u64, len: u64) -> Option<Self> {
+        Some(Self::new(start, start.checked_add(len)?))
+    }
+
+    pub fn len(&self) -> u64 {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    pub fn contains(&self, value: u64) -> bool {
+        self.start <= value && value < self.end
+    }
+
+    pub fn overlaps(&self, other: &Interval) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+
+    pub fn intersection(&self, other: &Interval) -> Option<Interval> {
+        let start = self.start.max(other.start);
+        let end = self.end.min(other.end);
+        (start < end).then_some(Interval { start, end })
+    }
+}
+
+// sorts the intervals and coalesces the ones that overlap or touch
+pub fn merge(intervals: &[Interval]) -> Vec<Interval> {
+    let mut sorted: Vec<Interval> = intervals.iter().filter(|i| !i.is_empty()).copied().collect();
+    sorted.sort();
+    let mut merged: Vec<Interval> = Vec::new();
+    for i in sorted {
+        match merged.last_mut() {
+            Some(last) if i.start <= last.end => last.end = last.end.max(i.end),
+            _ => merged.push(i),
+        }
+    }
+    merged
+}
+
+// values present in both sets of intervals, merged
+pub fn intersect_all(a: &[Interval], b: &[Interval]) -> Vec<Interval> {
+    let (a, b) = (merge(a), merge(b));
+    let mut result = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if let Some(common) = a[i].intersection(&b[j]) {
+            result.push(common);
+        }
+        if a[i].end < b[j].end {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    result
+}
+
+impl Display for Interval {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+#[test]
+fn test_interval_boundaries() {
+    let i = Interval::with_len(98, 2).unwrap();
+    assert_eq!(i, Interval::new(98, 100));
+    assert_eq!(i.len(), 2);
+    assert!(!i.contains(97));
+    assert!(i.contains(98));
+    assert!(i.contains(99));
+    assert!(!i.contains(100));
+
+    assert!(!i.overlaps(&Interval::new(100, 110)));
+    assert!(!i.overlaps(&Interval::new(90, 98)));
+    assert!(i.overlaps(&Interval::new(99, 110)));
+    assert_eq!(i.intersection(&Interval::new(100, 110)), None);
+    assert_eq!(i.intersection(&Interval::new(99, 110)), Some(Interval::new(99, 100)));
+
+    assert!(Interval::new(5, 5).is_empty());
+    assert!(!Interval::new(5, 5).contains(5));
+
+    assert_eq!(Interval::with_len(u64::MAX - 2, 2), Some(Interval::new(u64::MAX - 2, u64::MAX)));
+    assert_eq!(Interval::with_len(u64::MAX - 2, 3), None);
+}
+
+#[test]
+fn test_merge_and_intersect() {
+    let merged = merge(&[Interval::new(10, 20), Interval::new(0, 5), Interval::new(5, 8), Interval::new(15, 25), Interval::new(30, 30)]);
+    assert_eq!(merged, vec![Interval::new(0, 8), Interval::new(10, 25)]);
+
+    let common = intersect_all(&merged, &[Interval::new(6, 12), Interval::new(20, 40)]);
+    assert_eq!(common, vec![Interval::new(6, 8), Interval::new(10, 12), Interval::new(20, 25)]);
+}
@@ -0,0 +1,12 @@
+pub mod almanac;
+pub mod export;
+pub mod generator;
+pub mod graph;
+pub mod interval;
+pub mod parallel;
+pub mod parser;
+#[cfg(test)]
+mod properties;
+pub mod repl;
+pub mod stats;
+pub mod virtual_map;
@@ -1,328 +1,225 @@
-use std::collections::HashMap;
-use std::fs;
-use std::ops::RangeInclusive;
-use lyn::{Error, Scanner};
-
+use std::error::Error;
+use std::{env, io, process};
+use day5::export::{write_lineage, Format};
+use day5::generator::{self, Settings};
+use day5::graph;
+use day5::interval::Interval;
+use day5::parallel;
+use day5::almanac::{Almanac, SeedSpec};
+use day5::parser::{parse, parse_reader, ParseError};
+use day5::repl;
+use day5::stats;
+#[cfg(test)]
+use day5::almanac::{AlmanacError, Instruction, Stage, Step};
+#[cfg(test)]
+use day5::parser::{insert_range, parse_composed, parse_str};
+#[cfg(test)]
+use day5::virtual_map::{Range, VirtualMap};
+
+const DEFAULT_INPUT: &str = "./input.txt";
+
+// usage, where a file of `-` reads the almanac from stdin:
+//   day5 [--threads <n>] [file]
+//   day5 export <csv|json|table> [file]
+//   day5 coverage [file]
+//   day5 simplify [file]
+//   day5 stats [--buckets <n>] [file]
+//   day5 dot [--seed <n> | --range <start> <length>] [file]   (pipe into `dot -Tsvg`)
+//   day5 repl [file]
+//   day5 generate <seed> [--stages <n>] [--ranges <n>] [--magnitude <n>]
+//                        [--seeds <n>] [--gaps <p>] [--overlaps <p>]
 fn main() {
-    let al = parse("./input.txt").unwrap();
-
-    let mut ii = al.instructions();
-    ii.sort_by(|x, y| x.location.cmp(&y.location));
-    let lowest = ii.get(0).unwrap();
-
-    println!("Part 1 Answer: {:?}", lowest);
-
-    println!("Part 2 Answer: {:?}", al.part2());
-}
-
-#[derive(PartialEq, Eq, Debug)]
-struct Almanac {
-    seeds: Vec<usize>,
-    seed_soil: VirtualMap,
-    soil_fertilizer: VirtualMap,
-    fertilizer_water: VirtualMap,
-    water_light: VirtualMap,
-    light_temp: VirtualMap,
-    temp_humidity: VirtualMap,
-    humidity_location: VirtualMap,
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("export") => export(&args[1..]),
+        Some("coverage") => coverage(&args[1..]),
+        Some("simplify") => simplify(&args[1..]),
+        Some("stats") => statistics(&args[1..]),
+        Some("repl") => query(&args[1..]),
+        Some("dot") => render(&args[1..]),
+        Some("generate") => generate(&args[1..]),
+        Some("--threads") => solve_parallel(&args[1..]),
+        _ => solve(args.first().map_or(DEFAULT_INPUT, String::as_str)),
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
 }
 
-impl Almanac {
-    fn instructions(&self) -> Vec<Instruction> {
-        let mut v: Vec<Instruction> = Vec::new();
-        for seed in &self.seeds {
-            let seed = *seed;
-            let soil = self.seed_soil.get(&seed);
-            let fertilizer = self.soil_fertilizer.get(&soil);
-            let water = self.fertilizer_water.get(&fertilizer);
-            let light = self.water_light.get(&water);
-            let temperature = self.light_temp.get(&light);
-            let humidity = self.temp_humidity.get(&temperature);
-            let location = self.humidity_location.get(&humidity);
-
-            v.push(Instruction {
-                seed,
-                soil,
-                fertilizer,
-                water,
-                light,
-                temperature,
-                humidity,
-                location,
-            })
-        }
-        v
+fn load(filename: &str) -> Result<Almanac, ParseError> {
+    match filename {
+        "-" => parse_reader(io::stdin().lock()),
+        _ => parse(filename),
     }
+}
 
-    fn seed_ranges(&self) -> Vec<RangeInclusive<usize>> {
-        let mut rr = Vec::new();
-        for chunk in self.seeds.chunks(2) {
-            match chunk {
-                &[start, len] => {
-                    let r = start..=start+len;
-                    rr.push(r)
-                }
-                _ => {
-                    eprintln!("Incomplete pair!");
-                }
-            }
-        }
-        rr
-    }
+fn solve(filename: &str) -> Result<(), Box<dyn Error>> {
+    let al = load(filename)?;
 
-    fn get_location(&self, seed: &usize) -> usize {
-        let soil = self.seed_soil.get(&seed);
-        let fertilizer = self.soil_fertilizer.get(&soil);
-        let water = self.fertilizer_water.get(&fertilizer);
-        let light = self.water_light.get(&water);
-        let temperature = self.light_temp.get(&light);
-        let humidity = self.temp_humidity.get(&temperature);
-        self.humidity_location.get(&humidity)
-    }
+    let lowest = al.part1()?.ok_or("no seeds in almanac")?;
+    println!("Part 1 Answer: {}", lowest);
 
-    fn seed_for_loc(&self, loc: usize) -> Option<usize> {
-        let humidity = self.humidity_location.rget(&loc);
-        let temp = self.temp_humidity.rget(&humidity);
-        let light = self.light_temp.rget(&temp);
-        let water = self.water_light.rget(&light);
-        let fertilizer = self.fertilizer_water.rget(&water);
-        let soil = self.soil_fertilizer.rget(&fertilizer);
-        let seed = self.seed_soil.rget(&soil);
-        for r in self.seed_ranges() {
-            if r.contains(&seed) {
-                return Some(seed)
-            }
-        }
-        None
-    }
-
-    fn part2(&self) -> usize {
-        let mut i = 0;
-        loop {
-            let seed = self.seed_for_loc(i);
-            if seed.is_some() {
-                return i
-            }
-            i += 1;
-        }
-    }
+    let lowest = al.part2()?.ok_or("no seed ranges in almanac")?;
+    println!("Part 2 Answer: {}", lowest);
+    Ok(())
 }
 
+fn solve_parallel(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let threads: usize = args.first().ok_or("missing thread count")?.parse()?;
+    let al = load(args.get(1).map_or(DEFAULT_INPUT, String::as_str))?;
 
+    let lowest = parallel::lowest_location(&al, &SeedSpec::list(&al.seeds)?, threads)?.ok_or("no seeds in almanac")?;
+    println!("Part 1 Answer: {}", lowest);
 
-
-#[derive(PartialEq, Eq, Debug)]
-struct Instruction {
-    seed: usize,
-    soil: usize,
-    fertilizer: usize,
-    water: usize,
-    light: usize,
-    temperature: usize,
-    humidity: usize,
-    location: usize,
+    let lowest = parallel::lowest_location(&al, &SeedSpec::pairs(&al.seeds)?, threads)?.ok_or("no seed ranges in almanac")?;
+    println!("Part 2 Answer: {}", lowest);
+    Ok(())
 }
 
-fn pop_until_alpha(scanner: &mut Scanner) {
-    while scanner.peek().is_some_and(|c| !c.is_alphanumeric()) {
-        scanner.pop();
-    };
-}
+fn export(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let format: Format = args.first().ok_or("missing export format")?.parse()?;
+    let al = load(args.get(1).map_or(DEFAULT_INPUT, String::as_str))?;
 
-// takes the whole line
-fn map_identifier(scanner: &mut Scanner) -> Option<String> {
-    let mut buf = String::new();
-    while scanner.peek().is_some_and(|c| c != &' ') {
-        buf.push(*scanner.pop().unwrap())
-    }
-    consume(scanner, " map:\n");
-    if buf.len() > 0 {
-        return Some(buf);
-    }
-    None
+    write_lineage(&mut io::stdout().lock(), &al.instructions()?, format)?;
+    Ok(())
 }
 
-fn maps(scanner: &mut Scanner) -> Result<HashMap<String, VirtualMap>, Error> {
-    let mut r: HashMap<String, VirtualMap> = HashMap::new();
-
-    while !scanner.is_done() {
-        pop_until_alpha(scanner);
-        let id = map_identifier(scanner);
-        let mut m = VirtualMap::new();
+fn coverage(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let al = load(args.first().map_or(DEFAULT_INPUT, String::as_str))?;
 
-        while scanner.peek().is_some_and(|c| c != &'\n') {
-            let range_def = number_list(scanner);
-            insert_range(&mut m, range_def[0], range_def[1], range_def[2]);
-        }
-
-        r.insert(id.unwrap().clone(), m);
+    let domain = al.domain();
+    for stage in &al.stages {
+        println!("{}-to-{}", stage.source, stage.destination);
+        print!("{}", stage.map.coverage(domain));
     }
-
-    return Ok(r);
+    Ok(())
 }
 
-fn parse(filename: &str) -> Result<Almanac, Error> {
-    let contents = match fs::read_to_string(filename) {
-        Ok(content) => { content }
-        Err(e) => { panic!("Error reading file: {}", e); }
-    };
+fn simplify(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut al = load(args.first().map_or(DEFAULT_INPUT, String::as_str))?;
 
-    let mut scanner = Scanner::new(contents.as_str());
-    let seeds = seeds(&mut scanner);
-    let mut maps = maps(&mut scanner)?;
-
-    return Ok(Almanac {
-        seeds,
-        seed_soil: maps.remove("seed-to-soil").unwrap(),
-        soil_fertilizer: maps.remove("soil-to-fertilizer").unwrap(),
-        fertilizer_water: maps.remove("fertilizer-to-water").unwrap(),
-        water_light: maps.remove("water-to-light").unwrap(),
-        light_temp: maps.remove("light-to-temperature").unwrap(),
-        temp_humidity: maps.remove("temperature-to-humidity").unwrap(),
-        humidity_location: maps.remove("humidity-to-location").unwrap(),
-    });
+    al.simplify();
+    print!("{}", al);
+    Ok(())
 }
 
-fn number_list(scanner: &mut Scanner) -> Vec<usize> {
-    let mut v: Vec<usize> = Vec::new();
-    let mut number_buf = String::new();
-    loop {
-        let c = scanner.pop();
-        match c {
-            None => {
-                if !number_buf.is_empty() {
-                    v.push(number_buf.parse::<usize>().unwrap());
-                }
-                break
-            }
-            Some(char) => {
-                if char.is_numeric() {
-                    number_buf.push(*char);
-                } else {
-                    if !number_buf.is_empty() {
-                        v.push(number_buf.parse::<usize>().unwrap());
-                        number_buf = String::new();
-                    }
-                    if char == &'\n' {
-                        break;
-                    }
-                }
-            }
-        };
-    }
+fn statistics(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let (buckets, rest) = match args.first().map(String::as_str) {
+        Some("--buckets") => (args.get(1).ok_or("missing bucket count")?.parse()?, &args[2..]),
+        _ => (10, args),
+    };
+    let al = load(rest.first().map_or(DEFAULT_INPUT, String::as_str))?;
 
-    v
+    let stats = stats::location_stats(&al, &SeedSpec::pairs(&al.seeds)?, buckets)?.ok_or("no seed ranges in almanac")?;
+    print!("{}", stats);
+    Ok(())
 }
 
-fn consume(scanner: &mut Scanner, str: &str) -> bool {
-    for char in str.chars() {
-        if !scanner.take(&char) {
-            return false;
+fn render(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let (highlight, rest) = match args.first().map(String::as_str) {
+        Some("--seed") => {
+            let seed: u64 = args.get(1).ok_or("missing seed")?.parse()?;
+            (Some(Interval::with_len(seed, 1).ok_or("seed is past the largest value")?), &args[2..])
         }
-    }
-    return true;
-}
-
-fn seeds(scanner: &mut Scanner) -> Vec<usize> {
-    consume(scanner, "seeds:");
-    number_list(scanner)
-}
+        Some("--range") => {
+            let start: u64 = args.get(1).ok_or("missing range start")?.parse()?;
+            let length: u64 = args.get(2).ok_or("missing range length")?.parse()?;
+            (Some(Interval::with_len(start, length).ok_or("range goes past the largest value")?), &args[3..])
+        }
+        _ => (None, args),
+    };
+    let al = load(rest.first().map_or(DEFAULT_INPUT, String::as_str))?;
 
-fn insert_range(map: &mut VirtualMap, destination_start: usize, source_start: usize, length: usize) {
-    map.insert(source_start, length, destination_start);
+    print!("{}", graph::dot(&al, highlight)?);
+    Ok(())
 }
 
-#[derive(PartialEq, Eq, Debug, Clone)]
-struct Range {
-    source_range: RangeInclusive<usize>,
-    destination_range: RangeInclusive<usize>,
-    // destination_start: usize,
-}
+fn query(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let al = load(args.first().map_or(DEFAULT_INPUT, String::as_str))?;
 
-#[derive(PartialEq, Eq, Debug, Clone)]
-struct VirtualMap {
-    ranges: Vec<Range>
+    repl::run(&al, io::stdin().lock(), io::stdout().lock())?;
+    Ok(())
 }
 
-impl VirtualMap {
-    fn new() -> Self {
-        Self {
-            ranges: vec![],
+fn generate(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let seed: u64 = args.first().ok_or("missing generator seed")?.parse()?;
+    let mut settings = Settings::default();
+    for option in args[1..].chunks(2) {
+        let value = option.get(1).ok_or_else(|| format!("missing value for {}", option[0]))?;
+        match option[0].as_str() {
+            "--stages" => settings.stages = value.parse()?,
+            "--ranges" => settings.ranges_per_map = value.parse()?,
+            "--magnitude" => settings.magnitude = value.parse()?,
+            "--seeds" => settings.seed_pairs = value.parse()?,
+            "--gaps" => settings.gap_density = value.parse()?,
+            "--overlaps" => settings.overlap_density = value.parse()?,
+            o => return Err(format!("unknown option {}", o).into()),
         }
     }
-
-    fn insert(&mut self, source_start: usize, length: usize, destination_start: usize) {
-        let source_range = source_start..=source_start + length;
-        let destination_range = destination_start..=destination_start + length;
-        self.ranges.push(Range{
-            source_range,
-            destination_range,
-        });
+    if settings.magnitude == 0 {
+        return Err("magnitude must be at least 1".into());
     }
 
-    fn get(&self, query: &usize) -> usize {
-        for r in &self.ranges {
-            if r.source_range.contains(query) {
-                return r.destination_range.start() + (query - r.source_range.start())
-            }
-        }
-        query.clone()
-    }
+    print!("{}", generator::generate(seed, &settings));
+    Ok(())
+}
 
-    fn rget(&self, query: &usize) -> usize {
-        for r in &self.ranges {
-            if r.destination_range.contains(query) {
-                return r.source_range.start() + (query - r.destination_range.start())
-            }
-        }
-        query.clone()
+#[cfg(test)]
+fn stage(source: &str, destination: &str, map: VirtualMap) -> Stage {
+    Stage {
+        source: source.to_string(),
+        destination: destination.to_string(),
+        map,
     }
 }
 
-
+#[cfg(test)]
 fn expected_almanac() -> Almanac {
     let mut seed_soil = VirtualMap::new();
-    insert_range(&mut seed_soil, 50, 98, 2);
-    insert_range(&mut seed_soil, 52, 50, 48);
+    insert_range(&mut seed_soil, 50, 98, 2).unwrap();
+    insert_range(&mut seed_soil, 52, 50, 48).unwrap();
 
     let mut soil_fertilizer = VirtualMap::new();
-    insert_range(&mut soil_fertilizer, 0, 15, 37);
-    insert_range(&mut soil_fertilizer, 37, 52, 2);
-    insert_range(&mut soil_fertilizer, 39, 0, 15);
+    insert_range(&mut soil_fertilizer, 0, 15, 37).unwrap();
+    insert_range(&mut soil_fertilizer, 37, 52, 2).unwrap();
+    insert_range(&mut soil_fertilizer, 39, 0, 15).unwrap();
 
     let mut fertilizer_water = VirtualMap::new();
-    insert_range(&mut fertilizer_water, 49, 53, 8);
-    insert_range(&mut fertilizer_water, 0, 11, 42);
-    insert_range(&mut fertilizer_water, 42, 0, 7);
-    insert_range(&mut fertilizer_water, 57, 7, 4);
+    insert_range(&mut fertilizer_water, 49, 53, 8).unwrap();
+    insert_range(&mut fertilizer_water, 0, 11, 42).unwrap();
+    insert_range(&mut fertilizer_water, 42, 0, 7).unwrap();
+    insert_range(&mut fertilizer_water, 57, 7, 4).unwrap();
 
     let mut water_light = VirtualMap::new();
-    insert_range(&mut water_light, 88, 18, 7);
-    insert_range(&mut water_light, 18, 25, 70);
+    insert_range(&mut water_light, 88, 18, 7).unwrap();
+    insert_range(&mut water_light, 18, 25, 70).unwrap();
 
     let mut light_temp = VirtualMap::new();
-    insert_range(&mut light_temp, 45, 77, 23);
-    insert_range(&mut light_temp, 81, 45, 19);
-    insert_range(&mut light_temp, 68, 64, 13);
+    insert_range(&mut light_temp, 45, 77, 23).unwrap();
+    insert_range(&mut light_temp, 81, 45, 19).unwrap();
+    insert_range(&mut light_temp, 68, 64, 13).unwrap();
 
     let mut temp_humidity = VirtualMap::new();
-    insert_range(&mut temp_humidity, 0, 69, 1);
-    insert_range(&mut temp_humidity, 1, 0, 69);
+    insert_range(&mut temp_humidity, 0, 69, 1).unwrap();
+    insert_range(&mut temp_humidity, 1, 0, 69).unwrap();
 
     let mut humidity_location = VirtualMap::new();
-    insert_range(&mut humidity_location, 60, 56, 37);
-    insert_range(&mut humidity_location, 56, 93, 4);
+    insert_range(&mut humidity_location, 60, 56, 37).unwrap();
+    insert_range(&mut humidity_location, 56, 93, 4).unwrap();
 
 
     Almanac {
         seeds: vec![79, 14, 55, 13],
-        seed_soil,
-        soil_fertilizer,
-        fertilizer_water,
-        water_light,
-        light_temp,
-        temp_humidity,
-        humidity_location,
+        stages: vec![
+            stage("seed", "soil", seed_soil),
+            stage("soil", "fertilizer", soil_fertilizer),
+            stage("fertilizer", "water", fertilizer_water),
+            stage("water", "light", water_light),
+            stage("light", "temperature", light_temp),
+            stage("temperature", "humidity", temp_humidity),
+            stage("humidity", "location", humidity_location),
+        ],
     }
 }
 
@@ -337,54 +234,254 @@ fn test_parse() {
 }
 
 
+#[cfg(test)]
+fn range(destination_start: u64, source_start: u64, length: u64) -> Option<Range> {
+    Range::new(Interval::with_len(source_start, length).unwrap(), destination_start).ok()
+}
+
 #[test]
 fn test_instructions() {
     let almanac = expected_almanac();
 
-    let expected_instructions = vec![
-        Instruction {
-            seed: 79,
-            soil: 81,
-            fertilizer: 81,
-            water: 81,
-            light: 74,
-            temperature: 78,
-            humidity: 78,
-            location: 82,
-        },
-        Instruction {
-            seed: 14,
-            soil: 14,
-            fertilizer: 53,
-            water: 49,
-            light: 42,
-            temperature: 42,
-            humidity: 43,
-            location: 43,
-        },
-        Instruction {
-            seed: 55,
-            soil: 57,
-            fertilizer: 57,
-            water: 53,
-            light: 46,
-            temperature: 82,
-            humidity: 82,
-            location: 86,
-        },
-        Instruction {
-            seed: 13,
-            soil: 13,
-            fertilizer: 52,
-            water: 41,
-            light: 34,
-            temperature: 34,
-            humidity: 35,
-            location: 35,
-        },
+    let expected_values = vec![
+        (79, vec![81, 81, 81, 74, 78, 78, 82]),
+        (14, vec![14, 53, 49, 42, 42, 43, 43]),
+        (55, vec![57, 57, 53, 46, 82, 82, 86]),
+        (13, vec![13, 52, 41, 34, 34, 35, 35]),
     ];
 
-    let instructions = almanac.instructions();
+    let instructions = almanac.instructions().unwrap();
+    let values: Vec<(u64, Vec<u64>)> = instructions
+        .iter()
+        .map(|i| (i.seed, i.steps.iter().map(|s| s.value).collect()))
+        .collect();
+
+    assert_eq!(values, expected_values);
+
+    let categories = ["soil", "fertilizer", "water", "light", "temperature", "humidity", "location"];
+    let ranges = [range(52, 50, 48), None, None, range(18, 25, 70), range(68, 64, 13), None, range(60, 56, 37)];
+    let expected_steps: Vec<Step> = categories
+        .iter()
+        .zip(expected_values[0].1.iter())
+        .zip(ranges)
+        .map(|((category, value), range)| Step { category: category.to_string(), value: *value, range })
+        .collect();
+
+    assert_eq!(instructions[0], Instruction { seed: 79, steps: expected_steps });
+}
+
+#[test]
+fn test_part2() {
+    let almanac = parse("./test-input.txt").unwrap();
+
+    assert_eq!(almanac.part2(), Ok(Some(46)));
+    let pairs = SeedSpec::pairs(&almanac.seeds).unwrap();
+    assert_eq!(almanac.part2(), almanac.lowest_location_bruteforce(&pairs));
+
+    for instruction in almanac.instructions().unwrap() {
+        assert_eq!(almanac.get_location(&instruction.seed), Ok(instruction.location()));
+    }
+}
+
+#[test]
+fn test_compose() {
+    let almanac = expected_almanac();
+    let seed_soil = &almanac.stages[0].map;
+    let soil_fertilizer = &almanac.stages[1].map;
+
+    let seed_fertilizer = seed_soil.compose(soil_fertilizer);
+    for seed in 0..=120 {
+        let expected = soil_fertilizer.get(&seed_soil.get(&seed));
+        assert_eq!(seed_fertilizer.get(&seed), expected);
+    }
+
+    let seed_location = almanac.seed_location_map().unwrap();
+    for seed in 0..=120 {
+        assert_eq!(Ok(seed_location.get(&seed)), almanac.get_location(&seed));
+    }
+    assert_eq!(seed_location.get(&u64::MAX), u64::MAX);
+}
+
+#[test]
+fn test_convert() {
+    let mut almanac = expected_almanac();
+
+    assert_eq!(almanac.convert("seed", "humidity", 79), Ok(78));
+    assert_eq!(almanac.convert("water", "location", 81), Ok(82));
+    assert_eq!(almanac.convert("location", "seed", 82), Ok(79));
+    assert_eq!(almanac.convert("soil", "soil", 5), Ok(5));
+    assert_eq!(almanac.convert("seed", "weather", 5), Err(AlmanacError::MissingLink("location".to_string())));
+
+    almanac.stages.push(stage("location", "seed", VirtualMap::new()));
+    assert_eq!(almanac.convert("seed", "weather", 5), Err(AlmanacError::Cycle("seed".to_string())));
+
+    almanac.stages.push(stage("seed", "weather", VirtualMap::new()));
+    assert_eq!(almanac.convert("seed", "location", 5), Err(AlmanacError::AmbiguousLink("seed".to_string())));
+}
+
+#[test]
+fn test_extra_stage() {
+    let mut almanac = expected_almanac();
+    let mut location_region = VirtualMap::new();
+    insert_range(&mut location_region, 1000, 80, 10).unwrap();
+    almanac.stages.insert(3, stage("location", "region", location_region));
+
+    assert_eq!(almanac.convert("seed", "region", 79), Ok(1002));
+    assert_eq!(almanac.instructions().unwrap()[0].location(), 82);
+}
+
+#[test]
+fn test_seed_range_boundaries() {
+    let almanac = parse_str("seeds: 10 5\n\nseed-to-location map:\n0 15 1\n1 9 1\n").unwrap();
+
+    let pairs = SeedSpec::pairs(&almanac.seeds).unwrap();
+    assert_eq!(pairs, SeedSpec::Ranges(vec![Interval::new(10, 15)]));
+    assert_eq!(almanac.seed_for_loc(0, &pairs), Ok(None));
+    assert_eq!(almanac.seed_for_loc(1, &pairs), Ok(None));
+    assert_eq!(almanac.seed_for_loc(10, &pairs), Ok(Some(10)));
+    assert_eq!(almanac.part2(), Ok(Some(10)));
+    assert_eq!(almanac.lowest_location_bruteforce(&pairs), Ok(Some(10)));
+}
+
+#[test]
+fn test_print_round_trip() {
+    let almanac = expected_almanac();
+
+    let printed = almanac.to_string();
+    assert!(printed.starts_with("seeds: 79 14 55 13\n\nseed-to-soil map:\n52 50 48\n50 98 2\n\nsoil-to-fertilizer map:\n"));
+    assert!(printed.ends_with("humidity-to-location map:\n60 56 37\n56 93 4\n"));
+    assert_eq!(parse_str(&printed).unwrap(), almanac);
+
+    let mut almanac = Almanac { seeds: vec![], stages: vec![] };
+    assert_eq!(almanac.to_string(), "seeds:\n");
+    assert_eq!(parse_str(&almanac.to_string()).unwrap(), almanac);
 
-    assert_eq!(instructions, expected_instructions)
-}
\ No newline at end of file
+    almanac.stages.push(stage("a", "b", VirtualMap::new()));
+    almanac.stages.push(stage("b", "c", VirtualMap::new()));
+    assert_eq!(almanac.to_string(), "seeds:\n\na-to-b map:\n\nb-to-c map:\n");
+    assert_eq!(parse_str(&almanac.to_string()).unwrap(), almanac);
+}
+
+#[test]
+fn test_print_round_trip_composed() {
+    let almanac = parse_str("seeds: 3 1\n\nseed-to-soil map:\n50 0 10\n\nsoil-to-location map:\n50 100 10\n").unwrap();
+    let composed = Almanac {
+        seeds: almanac.seeds.clone(),
+        stages: vec![stage("seed", "location", almanac.seed_location_map().unwrap())],
+    };
+
+    // 0..10 and 100..110 both go to 50..60
+    let printed = composed.to_string();
+    assert_eq!(printed, "seeds: 3 1\n\nseed-to-location map:\n50 0 10\n50 100 10\n");
+    assert_eq!(parse_composed(printed.as_bytes()).unwrap(), composed);
+    assert!(matches!(parse_str(&printed), Err(ParseError::InvalidRange(_, _))));
+}
+
+#[test]
+fn test_print_round_trip_out_of_order() {
+    let almanac = parse_composed("seeds:\n\nseed-to-location map:\n50 100 10\n50 0 10\n".as_bytes()).unwrap();
+    let reordered = parse_composed("seeds:\n\nseed-to-location map:\n50 0 10\n50 100 10\n".as_bytes()).unwrap();
+    assert_eq!(almanac, reordered);
+    assert_eq!(parse_composed(almanac.to_string().as_bytes()).unwrap(), almanac);
+
+    let almanac = parse_str("seeds:\n\nseed-to-soil map:\n0 30 5\n10 20 5\n20 10 5\n").unwrap();
+    let reordered = parse_str("seeds:\n\nseed-to-soil map:\n20 10 5\n0 30 5\n10 20 5\n").unwrap();
+    assert_eq!(almanac, reordered);
+    assert_eq!(parse_str(&almanac.to_string()).unwrap(), almanac);
+}
+
+#[test]
+fn test_seeds_for_locations() {
+    let almanac = parse("./test-input.txt").unwrap();
+
+    let locations = Interval::new(40, 60);
+    let seeds = almanac.seeds_for_locations(locations, None).unwrap();
+    let expected: Vec<u64> = (0..200)
+        .filter(|s| locations.contains(almanac.get_location(s).unwrap()))
+        .collect();
+    let values: Vec<u64> = seeds.iter().flat_map(|i| i.start..i.end).filter(|s| *s < 200).collect();
+    assert_eq!(values, expected);
+
+    // 46 is the lowest location reachable from the seed ranges, through seed 82
+    let pairs = SeedSpec::pairs(&almanac.seeds).unwrap();
+    assert_eq!(almanac.seeds_for_locations(Interval::new(46, 47), Some(&pairs)), Ok(vec![Interval::new(82, 83)]));
+    assert_eq!(almanac.seeds_for_locations(Interval::new(0, 46), Some(&pairs)), Ok(vec![]));
+}
+
+#[test]
+fn test_seed_range_overflow() {
+    let almanac = parse_str("seeds: 18446744073709551610 6\n\nseed-to-location map:\n").unwrap();
+
+    assert_eq!(SeedSpec::pairs(&almanac.seeds), Err(AlmanacError::SeedRangeOverflow { start: u64::MAX - 5, length: 6 }));
+    assert_eq!(almanac.part2(), Err(AlmanacError::SeedRangeOverflow { start: u64::MAX - 5, length: 6 }));
+}
+
+#[test]
+fn test_bruteforce_unchanged_values() {
+    // seed 3 falls through unchanged onto the destination of 10 -> 3, which
+    // rget alone would send back to 10
+    let almanac = parse_str("seeds: 3 1\n\nseed-to-location map:\n3 10 1\n").unwrap();
+    let pairs = SeedSpec::pairs(&almanac.seeds).unwrap();
+
+    assert_eq!(almanac.seed_for_loc(3, &pairs), Ok(Some(3)));
+    assert_eq!(almanac.part2(), Ok(Some(3)));
+    assert_eq!(almanac.lowest_location_bruteforce(&pairs), Ok(Some(3)));
+}
+
+#[test]
+fn test_seed_spec() {
+    let mut almanac = parse("./test-input.txt").unwrap();
+
+    let list = SeedSpec::list(&almanac.seeds).unwrap();
+    assert_eq!(list, SeedSpec::List(vec![79, 14, 55, 13]));
+    assert!(list.contains(14) && !list.contains(15));
+    assert_eq!(almanac.part1(), Ok(Some(35)));
+    assert_eq!(almanac.lowest_location(&list), Ok(Some(35)));
+    assert_eq!(almanac.lowest_location_bruteforce(&list), Ok(Some(35)));
+
+    let pairs = SeedSpec::pairs(&almanac.seeds).unwrap();
+    assert_eq!(pairs.intervals(), vec![Interval::new(79, 93), Interval::new(55, 68)]);
+    assert!(pairs.contains(92) && !pairs.contains(93) && !pairs.contains(14));
+
+    almanac.seeds.push(7);
+    assert_eq!(almanac.get_location(&7), Ok(29));
+    assert_eq!(almanac.part1(), Ok(Some(29)));
+    assert_eq!(almanac.part2(), Err(AlmanacError::IncompletePair(5)));
+    assert_eq!(AlmanacError::IncompletePair(5).to_string(), "5 seed values can not be read as start and length pairs");
+
+    // no range can hold u64::MAX, so it is always its own location
+    let list = SeedSpec::list(&[u64::MAX, 7]).unwrap();
+    assert_eq!(list.intervals(), vec![Interval::new(7, 8)]);
+    assert_eq!(almanac.lowest_location(&list), Ok(Some(29)));
+    assert_eq!(parallel::lowest_location(&almanac, &list, 2).unwrap(), Some(29));
+    almanac.seeds = vec![u64::MAX];
+    assert_eq!(almanac.part1(), Ok(Some(u64::MAX)));
+    assert_eq!(parallel::lowest_location(&almanac, &SeedSpec::list(&almanac.seeds).unwrap(), 2).unwrap(), Some(u64::MAX));
+    let empty = SeedSpec::list(&[]).unwrap();
+    assert_eq!(almanac.lowest_location(&empty), Ok(None));
+    assert_eq!(almanac.lowest_location_bruteforce(&empty), Ok(None));
+}
+
+#[test]
+fn test_simplify() {
+    let mut almanac = expected_almanac();
+    let mut shuffled = parse_str(
+        "seeds: 79 14 55 13\n\n\
+         humidity-to-location map:\n56 93 4\n60 56 20\n80 76 17\n\n\
+         temperature-to-humidity map:\n0 69 1\n1 0 69\n\n\
+         seed-to-soil map:\n50 98 2\n52 50 48\n0 0 10\n\n\
+         soil-to-fertilizer map:\n39 0 15\n0 15 37\n37 52 2\n\n\
+         fertilizer-to-water map:\n0 11 42\n42 0 7\n57 7 4\n49 53 8\n\n\
+         water-to-light map:\n88 18 7\n18 25 70\n\n\
+         light-to-temperature map:\n45 77 23\n81 45 19\n68 64 13\n",
+    )
+    .unwrap();
+    assert_ne!(shuffled, almanac);
+
+    shuffled.simplify();
+    almanac.simplify();
+    assert_eq!(shuffled, almanac);
+    assert_eq!(shuffled.stages[0].source, "seed");
+    assert_eq!(shuffled.stages[6].map.ranges().len(), 2);
+    assert_eq!(shuffled.part2(), Ok(Some(46)));
+}
@@ -0,0 +1,87 @@
+use std::fmt::{Display, Formatter};
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+use crate::almanac::{Almanac, AlmanacError, SeedSpec, LOCATION, SEED};
+
+#[derive(Debug)]
+pub enum ParallelError {
+    Almanac(AlmanacError),
+    ThreadPool(ThreadPoolBuildError),
+}
+
+impl Display for ParallelError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParallelError::Almanac(e) => write!(f, "{}", e),
+            ParallelError::ThreadPool(e) => write!(f, "could not start worker threads: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ParallelError {}
+
+impl From<AlmanacError> for ParallelError {
+    fn from(e: AlmanacError) -> Self {
+        ParallelError::Almanac(e)
+    }
+}
+
+impl From<ThreadPoolBuildError> for ParallelError {
+    fn from(e: ThreadPoolBuildError) -> Self {
+        ParallelError::ThreadPool(e)
+    }
+}
+
+// 0 threads lets rayon pick one per CPU
+fn pool(threads: usize) -> Result<ThreadPool, ParallelError> {
+    Ok(ThreadPoolBuilder::new().num_threads(threads).build()?)
+}
+
+// lowest location of the seeds, each seed or seed range pushed through the
+// stages one after the other on its own thread. Composing the stages into a
+// single map first would do most of the work before the threads start
+pub fn lowest_location(almanac: &Almanac, seeds: &SeedSpec, threads: usize) -> Result<Option<u64>, ParallelError> {
+    let chain = almanac.chain(SEED, LOCATION)?;
+    let pool = pool(threads)?;
+    // single seeds are traced as values, u64::MAX having no interval
+    if let SeedSpec::List(list) = seeds {
+        return Ok(pool.install(|| list.par_iter().map(|s| chain.iter().fold(*s, |v, stage| stage.map.get(&v))).min()));
+    }
+    let intervals = seeds.intervals();
+    Ok(pool.install(|| {
+        intervals
+            .par_iter()
+            .filter_map(|r| {
+                let locations = chain.iter().fold(vec![*r], |values, stage| stage.map.get_ranges(&values));
+                locations.iter().filter(|l| !l.is_empty()).map(|l| l.start).min()
+            })
+            .min()
+    }))
+}
+
+#[test]
+fn test_parallel_matches_serial() {
+    let almanac = crate::parser::parse("./test-input.txt").unwrap();
+    let (list, pairs) = (SeedSpec::list(&almanac.seeds).unwrap(), SeedSpec::pairs(&almanac.seeds).unwrap());
+    let serial_part1 = almanac.instructions().unwrap().iter().map(|i| i.location()).min();
+    let serial_part2 = almanac.part2().unwrap();
+
+    for threads in [0, 1, 2, 4, 8] {
+        assert_eq!(lowest_location(&almanac, &list, threads).unwrap(), serial_part1);
+        assert_eq!(lowest_location(&almanac, &pairs, threads).unwrap(), serial_part2);
+    }
+}
+
+#[test]
+fn test_parallel_many_ranges() {
+    let mut almanac = crate::parser::parse("./test-input.txt").unwrap();
+    almanac.seeds = (0..500).flat_map(|i| [i * 7 % 113, i % 5]).collect();
+    let (list, pairs) = (SeedSpec::list(&almanac.seeds).unwrap(), SeedSpec::pairs(&almanac.seeds).unwrap());
+    let serial_part1 = almanac.instructions().unwrap().iter().map(|i| i.location()).min();
+    let serial_part2 = almanac.part2().unwrap();
+
+    for _ in 0..10 {
+        assert_eq!(lowest_location(&almanac, &list, 4).unwrap(), serial_part1);
+        assert_eq!(lowest_location(&almanac, &pairs, 4).unwrap(), serial_part2);
+    }
+}
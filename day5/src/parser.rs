@@ -0,0 +1,291 @@
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use crate::almanac::{Almanac, Stage};
+use crate::virtual_map::{MapError, VirtualMap};
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+#[derive(Debug)]
+pub enum ParseError {
+    Io(io::Error),
+    UnknownHeader(Position, String),
+    MissingMapSuffix(Position, String),
+    WrongNumberCount(Position, usize),
+    NotANumber(Position, String),
+    InvalidRange(Position, MapError),
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::Io(e) => write!(f, "error reading almanac: {}", e),
+            ParseError::UnknownHeader(p, header) => write!(f, "{}: unknown header `{}`", p, header),
+            ParseError::MissingMapSuffix(p, header) => write!(f, "{}: expected ` map:` after `{}`", p, header),
+            ParseError::WrongNumberCount(p, count) => write!(f, "{}: expected 3 numbers on a range line, found {}", p, count),
+            ParseError::NotANumber(p, token) => write!(f, "{}: `{}` is not a number", p, token),
+            ParseError::InvalidRange(p, e) => write!(f, "{}: {}", p, e),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ParseError {
+    fn from(e: io::Error) -> Self {
+        ParseError::Io(e)
+    }
+}
+
+// reads the input one line at a time into a reused buffer, keeping count of
+// the line number for error positions
+struct Lines<R> {
+    reader: R,
+    buf: String,
+    number: usize,
+}
+
+impl<R: BufRead> Lines<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buf: String::new(),
+            number: 0,
+        }
+    }
+
+    // reads the next line into the buffer, false at the end of the input
+    fn advance(&mut self) -> Result<bool, ParseError> {
+        self.buf.clear();
+        if self.reader.read_line(&mut self.buf)? == 0 {
+            return Ok(false);
+        }
+        self.number += 1;
+        Ok(true)
+    }
+
+    // the line read last, without its line break
+    fn current(&self) -> &str {
+        self.buf.trim_end_matches(['\n', '\r'])
+    }
+
+    // the next line and its number, None at the end of the input
+    fn next(&mut self) -> Result<Option<(usize, &str)>, ParseError> {
+        Ok(self.advance()?.then(|| (self.number, self.current())))
+    }
+
+    // same as next, skipping blank lines
+    fn next_filled(&mut self) -> Result<Option<(usize, &str)>, ParseError> {
+        while self.advance()? {
+            if !self.current().trim().is_empty() {
+                return Ok(Some((self.number, self.current())));
+            }
+        }
+        Ok(None)
+    }
+}
+
+// whitespace separated words of a line, with the column each one starts at
+fn words(line: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (column, (i, c)) in line.char_indices().enumerate() {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some((column + 1, i)),
+            (Some((column, s)), true) => {
+                words.push((column, &line[s..i]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some((column, s)) = start {
+        words.push((column, &line[s..]));
+    }
+    words
+}
+
+fn number_list(words: &[(usize, &str)], line: usize) -> Result<Vec<u64>, ParseError> {
+    words
+        .iter()
+        .map(|(column, word)| {
+            word.parse::<u64>()
+                .map_err(|_| ParseError::NotANumber(Position { line, column: *column }, word.to_string()))
+        })
+        .collect()
+}
+
+// takes the whole line
+fn map_identifier(line: &str, number: usize) -> Result<(String, String), ParseError> {
+    let trimmed = line.trim_start();
+    let (id, rest) = trimmed.split_at(trimmed.find(char::is_whitespace).unwrap_or(trimmed.len()));
+    let position = Position {
+        line: number,
+        column: 1 + line.chars().count() - trimmed.chars().count(),
+    };
+    if !rest.strip_prefix(" map:").is_some_and(|r| r.trim().is_empty()) {
+        return Err(ParseError::MissingMapSuffix(position, id.to_string()));
+    }
+    categories(id).ok_or(ParseError::UnknownHeader(position, id.to_string()))
+}
+
+// splits a `source-to-destination` identifier into its two categories
+fn categories(id: &str) -> Option<(String, String)> {
+    let (source, destination) = id.split_once("-to-")?;
+    if source.is_empty() || destination.is_empty() {
+        return None;
+    }
+    Some((source.to_string(), destination.to_string()))
+}
+
+fn seeds<R: BufRead>(lines: &mut Lines<R>) -> Result<Vec<u64>, ParseError> {
+    let (number, line) = lines.next()?.unwrap_or((1, ""));
+    let Some(rest) = line.strip_prefix("seeds:") else {
+        let header = line.split(char::is_whitespace).next().unwrap_or("").to_string();
+        return Err(ParseError::UnknownHeader(Position { line: number, column: 1 }, header));
+    };
+    // columns of the numbers count from the start of the line
+    let words: Vec<(usize, &str)> = words(rest).into_iter().map(|(c, w)| (c + "seeds:".len(), w)).collect();
+    number_list(&words, number)
+}
+
+// builds each map as its lines come in, so only the current line is held
+fn maps<R: BufRead>(lines: &mut Lines<R>, composed: bool) -> Result<Vec<Stage>, ParseError> {
+    let mut r: Vec<Stage> = Vec::new();
+
+    while let Some((number, header)) = lines.next_filled()? {
+        let (source, destination) = map_identifier(header, number)?;
+        let mut m = VirtualMap::new();
+
+        while let Some((number, line)) = lines.next()? {
+            let position = Position { line: number, column: 1 };
+            let range_def = number_list(&words(line), number)?;
+            match range_def[..] {
+                [] => break,
+                [destination_start, source_start, length] => {
+                    let inserted = if composed {
+                        m.insert_overlapping(source_start, length, destination_start)
+                    } else {
+                        insert_range(&mut m, destination_start, source_start, length)
+                    };
+                    inserted.map_err(|e| ParseError::InvalidRange(position, e))?
+                }
+                _ => return Err(ParseError::WrongNumberCount(position, range_def.len())),
+            }
+        }
+
+        r.push(Stage {
+            source,
+            destination,
+            map: m,
+        });
+    }
+
+    Ok(r)
+}
+
+pub fn parse(filename: &str) -> Result<Almanac, ParseError> {
+    parse_reader(BufReader::new(File::open(filename)?))
+}
+
+pub fn parse_str(contents: &str) -> Result<Almanac, ParseError> {
+    parse_reader(contents.as_bytes())
+}
+
+// reads an almanac from any buffered source, stdin included, line by line
+pub fn parse_reader(reader: impl BufRead) -> Result<Almanac, ParseError> {
+    read_almanac(reader, false)
+}
+
+// same as parse_reader, but lets the destinations of a map overlap, as they
+// may in a printed composed map
+pub fn parse_composed(reader: impl BufRead) -> Result<Almanac, ParseError> {
+    read_almanac(reader, true)
+}
+
+fn read_almanac(reader: impl BufRead, composed: bool) -> Result<Almanac, ParseError> {
+    let mut lines = Lines::new(reader);
+    let seeds = seeds(&mut lines)?;
+    let stages = maps(&mut lines, composed)?;
+
+    Ok(Almanac {
+        seeds,
+        stages,
+    })
+}
+
+pub fn insert_range(map: &mut VirtualMap, destination_start: u64, source_start: u64, length: u64) -> Result<(), MapError> {
+    map.insert(source_start, length, destination_start)
+}
+
+#[test]
+fn test_parse_errors() {
+    let error = parse_str("seeds: 1 2\n\nseed-to-soil map:\n50 98 2\n52 5O 48\n").unwrap_err();
+    assert!(matches!(error, ParseError::NotANumber(Position { line: 5, column: 4 }, ref t) if t == "5O"));
+    assert_eq!(error.to_string(), "line 5, column 4: `5O` is not a number");
+
+    let error = parse_str("seeds: 1 2\n\nseed-to-soil map:\n50 98\n").unwrap_err();
+    assert!(matches!(error, ParseError::WrongNumberCount(Position { line: 4, column: 1 }, 2)));
+
+    let error = parse_str("seeds: 1 2\n\nseed-to-soil:\n50 98 2\n").unwrap_err();
+    assert!(matches!(error, ParseError::MissingMapSuffix(Position { line: 3, column: 1 }, ref h) if h == "seed-to-soil:"));
+
+    let error = parse_str("seeds: 1 2\n\nseed-soil map:\n50 98 2\n").unwrap_err();
+    assert!(matches!(error, ParseError::UnknownHeader(Position { line: 3, column: 1 }, ref h) if h == "seed-soil"));
+
+    let error = parse_str("plants: 1 2\n").unwrap_err();
+    assert!(matches!(error, ParseError::UnknownHeader(Position { line: 1, column: 1 }, ref h) if h == "plants:"));
+
+    let error = parse_str("seeds: 1 2\n\nseed-to-soil map:\n50 98 2\n52 50 49\n").unwrap_err();
+    assert!(matches!(error, ParseError::InvalidRange(Position { line: 5, column: 1 }, MapError::SourceOverlap(_))));
+    assert_eq!(error.to_string(), "line 5, column 1: source range 50..99 overlaps an existing range");
+
+    let error = parse_str("seeds: 1 2\n\nseed-to-soil map:\n0 10 5\n2 20 5\n").unwrap_err();
+    assert!(matches!(error, ParseError::InvalidRange(Position { line: 5, column: 1 }, MapError::DestinationOverlap(_))));
+    assert_eq!(error.to_string(), "line 5, column 1: destination range 2..7 overlaps an existing range");
+
+    let error = parse_str("seeds: 1 2\n\nseed-to-soil map:\n0 18446744073709551610 6\n").unwrap_err();
+    assert!(matches!(error, ParseError::InvalidRange(Position { line: 4, column: 1 }, MapError::Overflow { .. })));
+
+    let error = parse_str("seeds: 18446744073709551616\n").unwrap_err();
+    assert!(matches!(error, ParseError::NotANumber(Position { line: 1, column: 8 }, _)));
+
+    let error = parse("./missing-input.txt").unwrap_err();
+    assert!(matches!(error, ParseError::Io(_)));
+}
+
+#[test]
+fn test_parse_blank_lines() {
+    let almanac = parse_str("seeds: 1 2\r\n\r\nseed-to-soil map:\r\n50 98 2\r\n\r\n").unwrap();
+    assert_eq!(almanac.seeds, vec![1, 2]);
+    assert_eq!(almanac.stages.len(), 1);
+}
+
+#[test]
+fn test_parse_reader() {
+    let contents = std::fs::read_to_string("./test-input.txt").unwrap();
+    let expected = parse("./test-input.txt").unwrap();
+    // a buffer smaller than a line makes every line span several reads
+    assert_eq!(parse_reader(BufReader::with_capacity(4, contents.as_bytes())).unwrap(), expected);
+    assert_eq!(parse_str(&contents).unwrap(), expected);
+
+    let error = parse_reader(&b"seeds: 1 2\n\nseed-to-soil map:\n\xff\n"[..]).unwrap_err();
+    assert!(matches!(error, ParseError::Io(_)));
+}
@@ -0,0 +1,79 @@
+// property tests comparing the solvers against each other and against brute
+// force, on small generated almanacs
+use proptest::prelude::*;
+use crate::almanac::{Almanac, SeedSpec, LOCATION, SEED};
+use crate::generator::{generate, Settings};
+use crate::interval::Interval;
+use crate::parallel;
+
+fn settings(bijective: bool) -> impl Strategy<Value = Settings> {
+    let overlap_density = if bijective { Just(0.0).boxed() } else { (0.0..=1.0).boxed() };
+    (1..5usize, 1..8usize, 1..300u64, 1..4usize, 0.0..=1.0, overlap_density).prop_map(
+        |(stages, ranges_per_map, magnitude, seed_pairs, gap_density, overlap_density)| Settings {
+            stages,
+            ranges_per_map,
+            magnitude,
+            seed_pairs,
+            gap_density,
+            overlap_density,
+        },
+    )
+}
+
+fn almanac(bijective: bool) -> impl Strategy<Value = (Almanac, Settings)> {
+    (any::<u64>(), settings(bijective)).prop_map(|(seed, settings)| (generate(seed, &settings), settings))
+}
+
+// lowest location over every seed of every range, one seed at a time
+fn forward_minimum(almanac: &Almanac) -> u64 {
+    SeedSpec::pairs(&almanac.seeds)
+        .unwrap()
+        .intervals()
+        .iter()
+        .flat_map(|r| r.start..r.end)
+        .map(|s| almanac.get_location(&s).unwrap())
+        .min()
+        .unwrap()
+}
+
+proptest! {
+    #[test]
+    fn rget_inverts_get_where_injective((almanac, settings) in almanac(false)) {
+        let domain = Interval::new(0, settings.magnitude);
+        for stage in &almanac.stages {
+            let coverage = stage.map.coverage(domain);
+            for x in domain.start..domain.end {
+                let y = stage.map.get(&x);
+                if !coverage.overlaps.iter().any(|o| o.contains(y)) {
+                    prop_assert_eq!(stage.map.rget(&y), x);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn forward_minimum_matches_reverse((almanac, _) in almanac(true)) {
+        let pairs = SeedSpec::pairs(&almanac.seeds).unwrap();
+        prop_assert_eq!(Ok(Some(forward_minimum(&almanac))), almanac.lowest_location_bruteforce(&pairs));
+    }
+
+    #[test]
+    fn interval_solvers_match_brute_force((almanac, settings) in almanac(false)) {
+        let expected = forward_minimum(&almanac);
+        let pairs = SeedSpec::pairs(&almanac.seeds).unwrap();
+        prop_assert_eq!(almanac.part2(), Ok(Some(expected)));
+        prop_assert_eq!(parallel::lowest_location(&almanac, &pairs, 2).unwrap(), Some(expected));
+
+        let composed = almanac.seed_location_map().unwrap();
+        for seed in 0..settings.magnitude + 10 {
+            prop_assert_eq!(Ok(composed.get(&seed)), almanac.get_location(&seed));
+        }
+
+        let locations = Interval::new(settings.magnitude / 3, settings.magnitude / 2 + 1);
+        let seeds = almanac.seeds_for_locations(locations, None).unwrap();
+        for seed in 0..settings.magnitude + 10 {
+            let reached = locations.contains(almanac.convert(SEED, LOCATION, seed).unwrap());
+            prop_assert_eq!(seeds.iter().any(|i| i.contains(seed)), reached);
+        }
+    }
+}
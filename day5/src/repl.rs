@@ -0,0 +1,153 @@
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+use crate::almanac::{Almanac, SeedSpec, LOCATION, SEED};
+use crate::interval::Interval;
+use crate::virtual_map::VirtualMap;
+
+const HELP: &str = "\
+seed <n>             trace a seed through every stage
+loc <n>              find the seed leading to a location
+range <start> <len>  lowest location of a seed range
+stage <x-to-y>       print a map
+help                 show this message
+quit                 leave";
+
+// keeps the almanac loaded between queries, along with the seed to location
+// map once a range query has needed it
+pub struct Session<'a> {
+    almanac: &'a Almanac,
+    seed_location: Option<VirtualMap>,
+}
+
+impl<'a> Session<'a> {
+    pub fn new(almanac: &'a Almanac) -> Self {
+        Self {
+            almanac,
+            seed_location: None,
+        }
+    }
+
+    // output of one command line, None when the session should end
+    pub fn command(&mut self, line: &str) -> Option<Result<String, Box<dyn Error>>> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let result = match words[..] {
+            [] => Ok(String::new()),
+            ["quit"] | ["exit"] => return None,
+            ["help"] => Ok(HELP.to_string()),
+            ["seed", n] => self.seed(n),
+            ["loc", n] => self.loc(n),
+            ["range", start, length] => self.range(start, length),
+            ["stage", name] => self.stage(name),
+            _ => Err(format!("unknown command `{}`, try help", line.trim()).into()),
+        };
+        Some(result)
+    }
+
+    fn seed(&self, n: &str) -> Result<String, Box<dyn Error>> {
+        let instruction = self.almanac.instruction(n.parse()?)?;
+        let mut lines = vec![format!("seed {}", instruction.seed)];
+        for step in &instruction.steps {
+            let range = step.range.as_ref().map_or("identity".to_string(), |r| r.to_string());
+            lines.push(format!("{} {} ({})", step.category, step.value, range));
+        }
+        Ok(lines.join("\n"))
+    }
+
+    // several seeds may lead to the location, so the lowest planted one is
+    // looked for in the whole preimage, falling back to the one rget finds
+    // when none is. With an odd number of seeds, they are taken one by one
+    fn loc(&self, n: &str) -> Result<String, Box<dyn Error>> {
+        let loc: u64 = n.parse()?;
+        let spec = SeedSpec::pairs(&self.almanac.seeds).or_else(|_| SeedSpec::list(&self.almanac.seeds))?;
+        let location = Interval::with_len(loc, 1).ok_or("location goes past the largest value")?;
+        let reaching = self.almanac.seeds_for_locations(location, Some(&spec))?;
+        Ok(match reaching.first() {
+            Some(planted) => format!("seed {} (in the seed ranges)", planted.start),
+            None => format!("seed {} (not in the seed ranges)", self.almanac.convert(LOCATION, SEED, loc)?),
+        })
+    }
+
+    fn range(&mut self, start: &str, length: &str) -> Result<String, Box<dyn Error>> {
+        let (start, length) = (start.parse()?, length.parse()?);
+        let range = Interval::with_len(start, length).ok_or("range goes past the largest value")?;
+        if self.seed_location.is_none() {
+            self.seed_location = Some(self.almanac.seed_location_map()?);
+        }
+        let map = self.seed_location.as_ref().unwrap();
+        let lowest = map.get_ranges(&[range]).iter().map(|l| l.start).min().ok_or("empty range")?;
+        Ok(format!("location {}", lowest))
+    }
+
+    fn stage(&self, name: &str) -> Result<String, Box<dyn Error>> {
+        let (source, destination) = name.split_once("-to-").ok_or("expected a stage like seed-to-soil")?;
+        let stage = self
+            .almanac
+            .stages
+            .iter()
+            .find(|s| s.source == source && s.destination == destination)
+            .ok_or_else(|| format!("no stage {}", name))?;
+        Ok(stage.to_string().trim_end().to_string())
+    }
+}
+
+pub fn run(almanac: &Almanac, input: impl BufRead, mut out: impl Write) -> io::Result<()> {
+    let mut session = Session::new(almanac);
+    write!(out, "> ")?;
+    out.flush()?;
+    for line in input.lines() {
+        match session.command(&line?) {
+            None => return Ok(()),
+            Some(Ok(output)) if output.is_empty() => {}
+            Some(Ok(output)) => writeln!(out, "{}", output)?,
+            Some(Err(e)) => writeln!(out, "error: {}", e)?,
+        }
+        write!(out, "> ")?;
+        out.flush()?;
+    }
+    writeln!(out)
+}
+
+#[test]
+fn test_session() {
+    let almanac = crate::parser::parse("./test-input.txt").unwrap();
+    let mut session = Session::new(&almanac);
+    let mut command = |line: &str| session.command(line).map(|r| r.map_err(|e| e.to_string()));
+
+    assert_eq!(
+        command("seed 79"),
+        Some(Ok("seed 79\nsoil 81 (50..98 -> 52..100)\nfertilizer 81 (identity)\nwater 81 (identity)\nlight 74 (25..95 -> 18..88)\ntemperature 78 (64..77 -> 68..81)\nhumidity 78 (identity)\nlocation 82 (56..93 -> 60..97)".to_string())),
+    );
+    assert_eq!(command("loc 82"), Some(Ok("seed 79 (in the seed ranges)".to_string())));
+    assert_eq!(command("loc 35"), Some(Ok("seed 13 (not in the seed ranges)".to_string())));
+    assert_eq!(command("range 79 14"), Some(Ok("location 46".to_string())));
+    assert_eq!(command("range 55 13"), Some(Ok("location 56".to_string())));
+    assert_eq!(command("stage light-to-temperature"), Some(Ok("light-to-temperature map:\n81 45 19\n68 64 13\n45 77 23".to_string())));
+    assert_eq!(command("stage light-to-soil"), Some(Err("no stage light-to-soil".to_string())));
+    assert_eq!(command("seed x"), Some(Err("invalid digit found in string".to_string())));
+    assert_eq!(command("fly"), Some(Err("unknown command `fly`, try help".to_string())));
+    assert_eq!(command(""), Some(Ok(String::new())));
+    assert_eq!(command("quit"), None);
+}
+
+#[test]
+fn test_loc_other_preimage() {
+    // seed 3 falls through unchanged onto 3, which rget sends back to 10
+    let almanac = crate::parser::parse_str("seeds: 3 1\n\nseed-to-location map:\n3 10 1\n").unwrap();
+    let mut session = Session::new(&almanac);
+    assert_eq!(session.command("loc 3").unwrap().unwrap(), "seed 3 (in the seed ranges)");
+    assert_eq!(session.command("loc 10").unwrap().unwrap(), "seed 10 (not in the seed ranges)");
+
+    let almanac = crate::parser::parse_str("seeds: 3 1 10\n\nseed-to-location map:\n3 10 1\n").unwrap();
+    let mut session = Session::new(&almanac);
+    assert_eq!(session.command("loc 3").unwrap().unwrap(), "seed 3 (in the seed ranges)");
+    assert_eq!(session.command("loc 1").unwrap().unwrap(), "seed 1 (in the seed ranges)");
+    assert_eq!(session.command("loc 5").unwrap().unwrap(), "seed 5 (not in the seed ranges)");
+}
+
+#[test]
+fn test_run() {
+    let almanac = crate::parser::parse("./test-input.txt").unwrap();
+    let mut out = Vec::new();
+    run(&almanac, "range 79 14\nloc x\nquit\nseed 79\n".as_bytes(), &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "> location 46\n> error: invalid digit found in string\n> ");
+}
@@ -0,0 +1,131 @@
+use std::fmt::{Display, Formatter};
+use crate::almanac::{Almanac, AlmanacError, SeedSpec};
+use crate::interval::{merge, Interval};
+
+// spread of the locations reached from a set of seeds
+#[derive(PartialEq, Eq, Debug)]
+pub struct LocationStats {
+    pub seeds: u128,
+    pub min: u64,
+    pub max: u64,
+    // pieces the seeds are cut into on their way to location
+    pub fragments: usize,
+    // what is left of them once the ones touching are merged
+    pub intervals: usize,
+    // equal width buckets from min to max, with the number of seeds landing
+    // in each
+    pub histogram: Vec<(Interval, u128)>,
+}
+
+impl Display for LocationStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "seeds {}", self.seeds)?;
+        writeln!(f, "min {}", self.min)?;
+        writeln!(f, "max {}", self.max)?;
+        writeln!(f, "fragments {}", self.fragments)?;
+        writeln!(f, "intervals {}", self.intervals)?;
+        for (bucket, count) in &self.histogram {
+            writeln!(f, "{} {}", bucket, count)?;
+        }
+        Ok(())
+    }
+}
+
+// pushes the seeds through the seed to location map as intervals and counts
+// how many values of each piece fall in each bucket, so the work depends on
+// the number of pieces and buckets rather than seeds. Seeds given more than
+// once are counted once. None without seeds
+pub fn location_stats(almanac: &Almanac, seeds: &SeedSpec, buckets: usize) -> Result<Option<LocationStats>, AlmanacError> {
+    let locations: Vec<Interval> = almanac
+        .seed_location_map()?
+        .get_ranges(&merge(&seeds.intervals()))
+        .into_iter()
+        .filter(|l| !l.is_empty())
+        .collect();
+    let (Some(min), Some(end)) = (locations.iter().map(|l| l.start).min(), locations.iter().map(|l| l.end).max()) else {
+        return Ok(None);
+    };
+
+    let width = (end - min).div_ceil(buckets.max(1) as u64);
+    let mut histogram: Vec<(Interval, u128)> = (min..end)
+        .step_by(width as usize)
+        .map(|start| (Interval::new(start, end.min(start.saturating_add(width))), 0))
+        .collect();
+    for l in &locations {
+        let first = ((l.start - min) / width) as usize;
+        let last = ((l.end - 1 - min) / width) as usize;
+        for (bucket, count) in &mut histogram[first..=last] {
+            *count += l.intersection(bucket).map_or(0, |i| i.len()) as u128;
+        }
+    }
+
+    Ok(Some(LocationStats {
+        seeds: locations.iter().map(|l| l.len() as u128).sum(),
+        min,
+        max: end - 1,
+        fragments: locations.len(),
+        intervals: merge(&locations).len(),
+        histogram,
+    }))
+}
+
+#[test]
+fn test_location_stats() {
+    let almanac = crate::parser::parse("./test-input.txt").unwrap();
+    let pairs = SeedSpec::pairs(&almanac.seeds).unwrap();
+    let stats = location_stats(&almanac, &pairs, 4).unwrap().unwrap();
+
+    let mut locations: Vec<u64> = pairs
+        .intervals()
+        .iter()
+        .flat_map(|r| r.start..r.end)
+        .map(|s| almanac.get_location(&s).unwrap())
+        .collect();
+    locations.sort();
+    assert_eq!(stats.seeds, locations.len() as u128);
+    assert_eq!(stats.min, 46);
+    assert_eq!(stats.max, *locations.last().unwrap());
+
+    let mut runs = locations.clone();
+    runs.dedup();
+    let intervals = 1 + runs.windows(2).filter(|w| w[1] > w[0] + 1).count();
+    assert_eq!(stats.intervals, intervals);
+    assert!(stats.fragments >= intervals);
+
+    assert_eq!(stats.histogram.len(), 4);
+    assert_eq!(stats.histogram[0].0.start, 46);
+    assert_eq!(stats.histogram[3].0.end, stats.max + 1);
+    for (bucket, count) in &stats.histogram {
+        assert_eq!(*count, locations.iter().filter(|l| bucket.contains(**l)).count() as u128);
+    }
+
+    assert_eq!(location_stats(&almanac, &SeedSpec::List(vec![]), 4), Ok(None));
+}
+
+#[test]
+fn test_location_stats_buckets() {
+    let almanac = crate::parser::parse_str("seeds: 0 10\n\nseed-to-location map:\n100 5 5\n").unwrap();
+    let pairs = SeedSpec::pairs(&almanac.seeds).unwrap();
+
+    // more buckets than values gives one bucket per value
+    let stats = location_stats(&almanac, &pairs, 1000).unwrap().unwrap();
+    assert_eq!((stats.min, stats.max, stats.fragments, stats.intervals), (0, 104, 2, 2));
+    assert_eq!(stats.histogram.len(), 105);
+    assert_eq!(stats.histogram.iter().filter(|(_, c)| *c == 1).count(), 10);
+
+    let stats = location_stats(&almanac, &pairs, 0).unwrap().unwrap();
+    assert_eq!(stats.histogram, vec![(Interval::new(0, 105), 10)]);
+    assert_eq!(stats.to_string(), "seeds 10\nmin 0\nmax 104\nfragments 2\nintervals 2\n0..105 10\n");
+}
+
+#[test]
+fn test_location_stats_overlapping_seeds() {
+    let almanac = crate::parser::parse_str("seeds: 0 10 5 10\n\nseed-to-location map:\n100 5 5\n").unwrap();
+    let pairs = SeedSpec::pairs(&almanac.seeds).unwrap();
+
+    // 5..10 is in both ranges
+    let stats = location_stats(&almanac, &pairs, 2).unwrap().unwrap();
+    assert_eq!(stats.seeds, 15);
+    assert_eq!(stats.histogram.iter().map(|(_, c)| c).sum::<u128>(), 15);
+    assert_eq!(stats.histogram, vec![(Interval::new(0, 53), 10), (Interval::new(53, 105), 5)]);
+}
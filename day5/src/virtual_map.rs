@@ -0,0 +1,519 @@
+use std::fmt::{Display, Formatter};
+use crate::interval::{merge, Interval};
+
+// source and destination always have the same length, so mapping a value of
+// the source can never overflow
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Range {
+    source_range: Interval,
+    destination_range: Interval,
+}
+
+impl Range {
+    pub fn new(source_range: Interval, destination_start: u64) -> Result<Self, MapError> {
+        let destination_range = Interval::with_len(destination_start, source_range.len()).ok_or(MapError::Overflow {
+            start: destination_start,
+            length: source_range.len(),
+        })?;
+        Ok(Self {
+            source_range,
+            destination_range,
+        })
+    }
+
+    pub fn source_range(&self) -> Interval {
+        self.source_range
+    }
+
+    pub fn destination_range(&self) -> Interval {
+        self.destination_range
+    }
+
+    // order of the ranges in by_destination
+    fn destination_key(&self) -> (u64, u64) {
+        (self.destination_range.start, self.source_range.start)
+    }
+
+    // query must lie in the source range, or be its end
+    pub fn map(&self, query: u64) -> u64 {
+        self.destination_range.start + (query - self.source_range.start)
+    }
+
+    pub fn rmap(&self, query: u64) -> u64 {
+        self.source_range.start + (query - self.destination_range.start)
+    }
+
+    // maps a piece of the source range onto the destination range
+    pub fn map_interval(&self, piece: Interval) -> Interval {
+        Interval::new(self.map(piece.start), self.map(piece.end))
+    }
+}
+
+impl Display for Range {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} -> {}", self.source_range, self.destination_range)
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum MapError {
+    SourceOverlap(Range),
+    DestinationOverlap(Range),
+    Overflow { start: u64, length: u64 },
+}
+
+impl Display for MapError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MapError::SourceOverlap(r) => write!(f, "source range {} overlaps an existing range", r.source_range),
+            MapError::DestinationOverlap(r) => write!(f, "destination range {} overlaps an existing range", r.destination_range),
+            MapError::Overflow { start, length } => write!(f, "range of length {} starting at {} goes past {}", length, start, u64::MAX),
+        }
+    }
+}
+
+impl std::error::Error for MapError {}
+
+// one `destination source length` line per range, in source order
+impl Display for VirtualMap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for r in &self.ranges {
+            writeln!(f, "{} {} {}", r.destination_range.start, r.source_range.start, r.source_range.len())?;
+        }
+        Ok(())
+    }
+}
+
+// how a map treats a domain: which parts are mapped explicitly, which fall
+// through unchanged, and which output values are reached more than once
+#[derive(PartialEq, Eq, Debug)]
+pub struct Coverage {
+    pub domain: Interval,
+    pub mapped: Vec<(Interval, Range)>,
+    pub gaps: Vec<Interval>,
+    pub overlaps: Vec<Interval>,
+    pub bijective: bool,
+}
+
+impl Display for Coverage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "domain {}", self.domain)?;
+        for (piece, r) in &self.mapped {
+            writeln!(f, "  mapped   {} -> {}", piece, r.map_interval(*piece))?;
+        }
+        for gap in &self.gaps {
+            writeln!(f, "  identity {}", gap)?;
+        }
+        for overlap in &self.overlaps {
+            writeln!(f, "  overlap  {}", overlap)?;
+        }
+        writeln!(f, "  bijective: {}", if self.bijective { "yes" } else { "no" })
+    }
+}
+
+// ranges are kept sorted by source start, and copied into a second list
+// sorted by destination then source start, so both directions are binary
+// searchable. Either list follows from the ranges alone, whatever order they
+// were inserted in, so maps with the same ranges compare equal
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct VirtualMap {
+    ranges: Vec<Range>,
+    by_destination: Vec<Range>,
+    // only maps built by compose or insert_overlapping may have overlapping
+    // destinations
+    destination_overlaps: bool,
+}
+
+impl VirtualMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // builds a map from ranges already sorted by source, with no source overlaps
+    fn from_sorted(ranges: Vec<Range>) -> Self {
+        let mut by_destination = ranges.clone();
+        by_destination.sort_by_key(Range::destination_key);
+        let destination_overlaps = by_destination
+            .windows(2)
+            .any(|w| w[0].destination_range.overlaps(&w[1].destination_range));
+        Self {
+            ranges,
+            by_destination,
+            destination_overlaps,
+        }
+    }
+
+    pub fn ranges(&self) -> &[Range] {
+        &self.ranges
+    }
+
+    // rejects ranges overlapping an existing one on either side; empty
+    // ranges map nothing and are dropped
+    pub fn insert(&mut self, source_start: u64, length: u64, destination_start: u64) -> Result<(), MapError> {
+        self.add(source_start, length, destination_start, false)
+    }
+
+    // like insert, but lets several sources map to the same values, as a
+    // composed map may
+    pub fn insert_overlapping(&mut self, source_start: u64, length: u64, destination_start: u64) -> Result<(), MapError> {
+        self.add(source_start, length, destination_start, true)
+    }
+
+    fn add(&mut self, source_start: u64, length: u64, destination_start: u64, allow_destination_overlaps: bool) -> Result<(), MapError> {
+        if length == 0 {
+            return Ok(());
+        }
+        let source_range = Interval::with_len(source_start, length).ok_or(MapError::Overflow {
+            start: source_start,
+            length,
+        })?;
+        let range = Range::new(source_range, destination_start)?;
+
+        let i = self.ranges.partition_point(|r| r.source_range.start < range.source_range.start);
+        let neighbours = &self.ranges[i.saturating_sub(1)..(i + 1).min(self.ranges.len())];
+        if neighbours.iter().any(|r| r.source_range.overlaps(&range.source_range)) {
+            return Err(MapError::SourceOverlap(range));
+        }
+
+        let j = self.by_destination.partition_point(|r| r.destination_key() < range.destination_key());
+        let neighbours = if self.destination_overlaps {
+            &self.by_destination[..]
+        } else {
+            &self.by_destination[j.saturating_sub(1)..(j + 1).min(self.by_destination.len())]
+        };
+        if neighbours.iter().any(|r| r.destination_range.overlaps(&range.destination_range)) {
+            if !allow_destination_overlaps {
+                return Err(MapError::DestinationOverlap(range));
+            }
+            self.destination_overlaps = true;
+        }
+
+        self.ranges.insert(i, range.clone());
+        self.by_destination.insert(j, range);
+        Ok(())
+    }
+
+    // the range whose source contains the query, if any
+    pub fn find(&self, query: &u64) -> Option<&Range> {
+        let i = self.ranges.partition_point(|r| r.source_range.start <= *query);
+        let r = &self.ranges[i.checked_sub(1)?];
+        r.source_range.contains(*query).then_some(r)
+    }
+
+    pub fn get(&self, query: &u64) -> u64 {
+        self.find(query).map_or(*query, |r| r.map(*query))
+    }
+
+    pub fn rget(&self, query: &u64) -> u64 {
+        let i = self.by_destination.partition_point(|r| r.destination_range.start <= *query);
+        let candidates = &self.by_destination[..i];
+        let found = if self.destination_overlaps {
+            candidates.iter().rev().find(|r| r.destination_range.contains(*query))
+        } else {
+            candidates.last().filter(|r| r.destination_range.contains(*query))
+        };
+        found.map_or(*query, |r| r.rmap(*query))
+    }
+
+    // splits the queries at range boundaries, pairing every piece with the
+    // range that maps it, or None where the identity fallback applies
+    pub fn pieces(&self, queries: &[Interval]) -> Vec<(Interval, Option<&Range>)> {
+        let mut pieces = Vec::new();
+        for q in queries {
+            if q.is_empty() {
+                continue;
+            }
+            let first = self.ranges.partition_point(|r| r.source_range.end <= q.start);
+            let mut cursor = q.start;
+            for r in self.ranges[first..].iter().take_while(|r| r.source_range.start < q.end) {
+                if cursor < r.source_range.start {
+                    pieces.push((Interval::new(cursor, r.source_range.start), None));
+                }
+                let end = q.end.min(r.source_range.end);
+                pieces.push((Interval::new(cursor.max(r.source_range.start), end), Some(r)));
+                cursor = end;
+            }
+            if cursor < q.end {
+                pieces.push((Interval::new(cursor, q.end), None));
+            }
+        }
+        pieces
+    }
+
+    pub fn get_ranges(&self, queries: &[Interval]) -> Vec<Interval> {
+        self.pieces(queries)
+            .into_iter()
+            .map(|(q, r)| r.map_or(q, |r| r.map_interval(q)))
+            .collect()
+    }
+
+    // every value that maps into one of the targets, merged
+    pub fn preimage(&self, targets: &[Interval]) -> Vec<Interval> {
+        let mut sources = Vec::new();
+        for t in targets {
+            let first = if self.destination_overlaps {
+                0
+            } else {
+                self.by_destination.partition_point(|r| r.destination_range.end <= t.start)
+            };
+            for r in &self.by_destination[first..] {
+                if r.destination_range.start >= t.end && !self.destination_overlaps {
+                    break;
+                }
+                if let Some(d) = r.destination_range.intersection(t) {
+                    sources.push(Interval::new(r.rmap(d.start), r.rmap(d.end)));
+                }
+            }
+            // values outside every source range map to themselves
+            sources.extend(self.pieces(&[*t]).into_iter().filter(|(_, r)| r.is_none()).map(|(p, _)| p));
+        }
+        merge(&sources)
+    }
+
+    pub fn coverage(&self, domain: Interval) -> Coverage {
+        let mut mapped = Vec::new();
+        let mut gaps = Vec::new();
+        let mut images = Vec::new();
+        for (piece, r) in self.pieces(&[domain]) {
+            match r {
+                Some(r) => {
+                    images.push(r.map_interval(piece));
+                    mapped.push((piece, r.clone()));
+                }
+                None => {
+                    images.push(piece);
+                    gaps.push(piece);
+                }
+            }
+        }
+
+        // sweeping the images by start, anything before the furthest end
+        // seen so far is reached twice
+        images.sort();
+        let mut overlaps = Vec::new();
+        let mut reach = None;
+        for image in &images {
+            if let Some(end) = reach.filter(|end| image.start < *end) {
+                overlaps.push(Interval::new(image.start, image.end.min(end)));
+            }
+            reach = reach.max(Some(image.end));
+        }
+        let overlaps = merge(&overlaps);
+        let bijective = overlaps.is_empty() && merge(&images) == vec![domain];
+
+        Coverage {
+            domain,
+            mapped,
+            gaps,
+            overlaps,
+            bijective,
+        }
+    }
+
+    // drops the ranges mapping values to themselves and merges neighbours
+    // that line up on both sides, so that maps giving the same results are
+    // laid out the same way
+    pub fn normalize(&mut self) {
+        let mut ranges: Vec<Range> = Vec::new();
+        for r in self.ranges.drain(..).filter(|r| r.source_range != r.destination_range) {
+            match ranges.last_mut() {
+                Some(last)
+                    if last.source_range.end == r.source_range.start
+                        && last.destination_range.end == r.destination_range.start =>
+                {
+                    last.source_range.end = r.source_range.end;
+                    last.destination_range.end = r.destination_range.end;
+                }
+                _ => ranges.push(r),
+            }
+        }
+        *self = VirtualMap::from_sorted(ranges);
+    }
+
+    // returns a single map equivalent to applying self, then next
+    pub fn compose(&self, next: &VirtualMap) -> VirtualMap {
+        let mut ranges = Vec::new();
+        for (piece, r) in self.pieces(&[Interval::new(0, u64::MAX)]) {
+            let image = r.map_or(piece, |r| r.map_interval(piece));
+            for (next_piece, next_r) in next.pieces(&[image]) {
+                let destination_range = next_r.map_or(next_piece, |r| r.map_interval(next_piece));
+                let source_start = piece.start + (next_piece.start - image.start);
+                let source_range = Interval::new(source_start, source_start + next_piece.len());
+                // identity pieces are covered by the fallback
+                if source_range != destination_range {
+                    ranges.push(Range {
+                        source_range,
+                        destination_range,
+                    });
+                }
+            }
+        }
+        VirtualMap::from_sorted(ranges)
+    }
+}
+
+#[test]
+fn test_insert_overlaps() {
+    let mut map = VirtualMap::new();
+    assert_eq!(map.insert(10, 5, 100), Ok(()));
+    assert_eq!(map.insert(0, 10, 200), Ok(()));
+    assert_eq!(map.insert(15, 5, 105), Ok(()));
+    assert_eq!(map.insert(20, 0, 100), Ok(()));
+
+    assert!(matches!(map.insert(14, 2, 300), Err(MapError::SourceOverlap(_))));
+    assert!(matches!(map.insert(5, 1, 300), Err(MapError::SourceOverlap(_))));
+    assert!(matches!(map.insert(30, 2, 109), Err(MapError::DestinationOverlap(_))));
+    assert!(matches!(map.insert(30, 2, 99), Err(MapError::DestinationOverlap(_))));
+    assert_eq!(map.ranges().len(), 3);
+
+    let starts: Vec<u64> = map.ranges().iter().map(|r| r.source_range.start).collect();
+    assert_eq!(starts, vec![0, 10, 15]);
+
+    assert!(matches!(map.insert_overlapping(14, 2, 300), Err(MapError::SourceOverlap(_))));
+    assert_eq!(map.insert_overlapping(30, 2, 109), Ok(()));
+    assert_eq!((map.get(&31), map.get(&19)), (110, 109));
+    assert_eq!(map.get(&map.rget(&110)), 110);
+}
+
+#[test]
+fn test_sorted_lookups() {
+    let mut map = VirtualMap::new();
+    for i in (0..100).rev() {
+        map.insert(i * 10, 5, 5000 - i * 10).unwrap();
+    }
+
+    for i in 0..100 {
+        assert_eq!(map.get(&(i * 10)), 5000 - i * 10);
+        assert_eq!(map.get(&(i * 10 + 4)), 5004 - i * 10);
+        assert_eq!(map.get(&(i * 10 + 5)), i * 10 + 5);
+        assert_eq!(map.rget(&(5000 - i * 10)), i * 10);
+        assert_eq!(map.rget(&(5004 - i * 10)), i * 10 + 4);
+    }
+    assert_eq!(map.rget(&5005), 5005);
+    assert_eq!(map.get(&u64::MAX), u64::MAX);
+}
+
+#[test]
+fn test_compose_overlapping_destinations() {
+    let mut first = VirtualMap::new();
+    first.insert(0, 10, 50).unwrap();
+    let mut second = VirtualMap::new();
+    second.insert(100, 10, 50).unwrap();
+
+    // 0..10 and 100..110 both end up on 50..60
+    let composed = first.compose(&second);
+    assert_eq!(composed.get(&3), 53);
+    assert_eq!(composed.get(&103), 53);
+    assert_eq!(composed.get(&composed.rget(&53)), 53);
+}
+
+#[test]
+fn test_range_boundaries() {
+    let mut map = VirtualMap::new();
+    map.insert(98, 2, 50).unwrap();
+    map.insert(50, 48, 52).unwrap();
+
+    assert_eq!(map.get(&49), 49);
+    assert_eq!(map.get(&50), 52);
+    assert_eq!(map.get(&97), 99);
+    assert_eq!(map.get(&98), 50);
+    assert_eq!(map.get(&99), 51);
+    assert_eq!(map.get(&100), 100);
+    assert_eq!(map.rget(&51), 99);
+    assert_eq!(map.rget(&52), 50);
+    assert_eq!(map.rget(&100), 100);
+
+    let pieces = map.get_ranges(&[Interval::new(95, 101)]);
+    assert_eq!(pieces, vec![Interval::new(97, 100), Interval::new(50, 52), Interval::new(100, 101)]);
+    assert!(map.insert(100, 1, 0).is_ok());
+    assert!(map.insert(97, 1, 200).is_err());
+}
+
+#[test]
+fn test_preimage() {
+    let mut map = VirtualMap::new();
+    map.insert(98, 2, 50).unwrap();
+    map.insert(50, 48, 52).unwrap();
+
+    let target = Interval::new(45, 60);
+    let expected: Vec<u64> = (0..200).filter(|x| target.contains(map.get(x))).collect();
+    let preimage = map.preimage(&[target]);
+    let values: Vec<u64> = preimage.iter().flat_map(|i| i.start..i.end).collect();
+    assert_eq!(values, expected);
+    assert_eq!(preimage, vec![Interval::new(45, 58), Interval::new(98, 100)]);
+}
+
+#[test]
+fn test_coverage() {
+    let mut map = VirtualMap::new();
+    map.insert(98, 2, 50).unwrap();
+    map.insert(50, 48, 52).unwrap();
+
+    let coverage = map.coverage(Interval::new(0, 100));
+    assert_eq!(coverage.mapped.len(), 2);
+    assert_eq!(coverage.gaps, vec![Interval::new(0, 50)]);
+    assert_eq!(coverage.overlaps, vec![]);
+    assert!(coverage.bijective);
+
+    // the images spill past the domain when it is cut through a range
+    assert!(!map.coverage(Interval::new(0, 99)).bijective);
+
+    // a hand-edited line sending 10..15 onto 0..5 collides with the identity
+    map.insert(10, 5, 0).unwrap();
+    let coverage = map.coverage(Interval::new(0, 100));
+    assert_eq!(coverage.gaps, vec![Interval::new(0, 10), Interval::new(15, 50)]);
+    assert_eq!(coverage.overlaps, vec![Interval::new(0, 5)]);
+    assert!(!coverage.bijective);
+    assert!(coverage.to_string().contains("  overlap  0..5\n  bijective: no"));
+}
+
+#[test]
+fn test_insert_overflow() {
+    let mut map = VirtualMap::new();
+    assert_eq!(map.insert(u64::MAX - 10, 10, 0), Ok(()));
+    assert_eq!(map.insert(u64::MAX - 30, 11, u64::MAX - 10), Err(MapError::Overflow { start: u64::MAX - 10, length: 11 }));
+    assert_eq!(map.insert(u64::MAX - 5, 6, 100), Err(MapError::Overflow { start: u64::MAX - 5, length: 6 }));
+
+    assert_eq!(map.get(&(u64::MAX - 1)), 9);
+    assert_eq!(map.get(&u64::MAX), u64::MAX);
+    assert_eq!(map.rget(&9), u64::MAX - 1);
+
+    let composed = map.compose(&map);
+    assert_eq!(composed.get(&(u64::MAX - 1)), 9);
+    assert_eq!(composed.get(&9), 9);
+}
+
+#[test]
+fn test_normalize() {
+    let mut map = VirtualMap::new();
+    map.insert(20, 5, 120).unwrap();
+    map.insert(0, 10, 100).unwrap();
+    map.insert(10, 10, 110).unwrap();
+    map.insert(25, 5, 25).unwrap();
+    map.insert(30, 5, 130).unwrap();
+    map.insert(40, 5, 200).unwrap();
+    map.insert(45, 5, 195).unwrap();
+    let before = map.clone();
+
+    map.normalize();
+    assert_eq!(map.to_string(), "100 0 25\n130 30 5\n200 40 5\n195 45 5\n");
+    for x in 0..300 {
+        assert_eq!(map.get(&x), before.get(&x));
+        assert_eq!(map.rget(&x), before.rget(&x));
+    }
+
+    let mut other = VirtualMap::new();
+    other.insert(45, 5, 195).unwrap();
+    other.insert(30, 5, 130).unwrap();
+    other.insert(0, 25, 100).unwrap();
+    other.insert(40, 5, 200).unwrap();
+    other.insert(60, 10, 60).unwrap();
+    assert_ne!(other, map);
+    other.normalize();
+    assert_eq!(other, map);
+
+    let mut identity = VirtualMap::new();
+    identity.insert(5, 5, 5).unwrap();
+    identity.normalize();
+    assert_eq!(identity, VirtualMap::new());
+}
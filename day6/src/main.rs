@@ -1,60 +1,264 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::{env, io, process};
+use number::{checked_mul, isqrt, n, Num};
+use parser::{parse_str, read};
+use report::{report, write_report, Format, RaceReport};
 
+mod number;
+mod parser;
+mod report;
+
+const DEFAULT_INPUT: &str = "./input.txt";
+
+// usage, where a file of `-` reads the races from stdin:
+//   day6 [file]
+//   day6 report <table|json> [--kerned] [file]
 fn main() {
-    let races = vec![
-        Race { duration: 40, record: 219 },
-        Race { duration: 81, record: 1012 },
-        Race { duration: 77, record: 1365 },
-        Race { duration: 72, record: 1089 },
-    ];
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("report") => race_report(&args[1..]),
+        _ => solve(args.first().map_or(DEFAULT_INPUT, String::as_str)),
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
 
-    println!("Part 1: {}", part1(races));
+fn solve(filename: &str) -> Result<(), Box<dyn Error>> {
+    let contents = read(filename)?;
 
-    println!("Part 2: {}", part1(vec![
-        Race{
-            duration: 40817772,
-            record: 219101213651089,
-        }
-    ]));
+    println!("Part 1: {}", part1(parse_str(&contents, false)?)?);
+    println!("Part 2: {}", part1(parse_str(&contents, true)?)?);
+    Ok(())
 }
 
+fn race_report(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let format: Format = args.first().ok_or("missing report format")?.parse()?;
+    let (kerned, rest) = match args.get(1).map(String::as_str) {
+        Some("--kerned") => (true, &args[2..]),
+        _ => (false, &args[1..]),
+    };
+    let races = parse_str(&read(rest.first().map_or(DEFAULT_INPUT, String::as_str))?, kerned)?;
+
+    let reports = races.iter().map(report).collect::<Result<Vec<RaceReport>, RaceError>>()?;
+    write_report(&mut io::stdout().lock(), &reports, format)?;
+    Ok(())
+}
+
+#[derive(PartialEq, Eq, Debug)]
 struct Race {
-    duration: usize,
-    record: usize,
+    duration: Num,
+    record: Num,
+}
+
+// the race is won when hold * (duration - hold) > record. The roots of that
+// parabola are (duration ± sqrt(duration² - 4 record)) / 2, so the integer
+// square root gives the first winning hold time to within one, which is then
+// fixed up by checking its neighbours. The winning hold times are symmetric
+// around duration / 2, so the last one is duration - first
+// the clones and references are needed when Num is a BigUint
+#[allow(clippy::clone_on_copy, clippy::op_ref)]
+fn first_winning_hold(race: &Race) -> Option<Num> {
+    let (t, d) = (&race.duration, &race.record);
+    // a distance too large to compute is past any record
+    let wins = |hold: &Num| checked_mul(hold, &(t - hold)).is_none_or(|distance| distance > *d);
+    let half = t / n(2);
+    if !wins(&half) {
+        return None;
+    }
+
+    let mut first = match (checked_mul(t, t), checked_mul(&n(4), d)) {
+        (Some(tt), Some(dd)) => (t - isqrt(&(tt - dd))) / n(2),
+        // duration² does not fit, so search the rising half of the parabola
+        // instead, in the same precision
+        _ => {
+            let (mut low, mut high) = (n(0), half.clone());
+            while low < high {
+                let middle = (&low + &high) / n(2);
+                if wins(&middle) {
+                    high = middle;
+                } else {
+                    low = middle + n(1);
+                }
+            }
+            low
+        }
+    };
+    while !wins(&first) {
+        first += n(1);
+    }
+    while first > n(0) && wins(&(&first - n(1))) {
+        first -= n(1);
+    }
+    Some(first)
 }
 
-fn winning_speeds_count(race: &Race) -> usize {
-    let mut winning_speeds = 0;
-    let mut speed: usize = 0;
-    loop {
-        let distance_traveled = speed * (race.duration - speed);
-        if distance_traveled > race.record {
+#[allow(clippy::op_ref)]
+fn winning_speeds_count(race: &Race) -> Num {
+    first_winning_hold(race).map_or(n(0), |first| &race.duration - first * n(2) + n(1))
+}
+
+#[cfg(test)]
+#[allow(clippy::clone_on_copy)]
+fn winning_speeds_count_bruteforce(race: &Race) -> Num {
+    let duration = u64::try_from(race.duration.clone()).unwrap();
+    let record = u64::try_from(race.record.clone()).unwrap();
+    let mut winning_speeds: u64 = 0;
+    let mut speed: u64 = 0;
+    while speed < duration {
+        let distance_traveled = speed * (duration - speed);
+        if distance_traveled > record {
             winning_speeds += 1;
         }
         speed += 1;
-        if speed == race.duration {
-            break
+    }
+    Num::from(winning_speeds)
+}
+
+#[derive(PartialEq, Eq, Debug)]
+enum Outcome {
+    // no time to move at all
+    ZeroDuration,
+    // even the best hold time does not beat the record
+    Unbeatable,
+    Count(Num),
+}
+
+impl Race {
+    fn outcome(&self) -> Outcome {
+        if self.duration == n(0) {
+            return Outcome::ZeroDuration;
+        }
+        match winning_speeds_count(self) {
+            count if count == n(0) => Outcome::Unbeatable,
+            count => Outcome::Count(count),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+enum RaceError {
+    // races are numbered from 1, in input order
+    ZeroDuration { race: usize },
+    Unbeatable { race: usize, duration: Num, record: Num },
+    Overflow,
+}
+
+impl Display for RaceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RaceError::ZeroDuration { race } => write!(f, "race {} lasts 0 ms, so it can not be won", race),
+            RaceError::Unbeatable { race, duration, record } => {
+                write!(f, "race {} can not beat its record of {} mm in {} ms", race, record, duration)
+            }
+            RaceError::Overflow => write!(f, "a result does not fit in a u128, build with --features bigint"),
         }
     }
-    winning_speeds
 }
 
-fn part1(races: Vec<Race>) -> usize {
-    races.iter().map(|r| winning_speeds_count(r)).fold(1, |acc, e| acc * e)
+impl std::error::Error for RaceError {}
+
+// a race that can not be won would bring the product down to 0, so it is
+// reported instead
+fn part1(races: Vec<Race>) -> Result<Num, RaceError> {
+    let mut product = n(1);
+    for (i, race) in races.into_iter().enumerate() {
+        let count = match race.outcome() {
+            Outcome::ZeroDuration => return Err(RaceError::ZeroDuration { race: i + 1 }),
+            Outcome::Unbeatable => {
+                return Err(RaceError::Unbeatable {
+                    race: i + 1,
+                    duration: race.duration,
+                    record: race.record,
+                })
+            }
+            Outcome::Count(count) => count,
+        };
+        product = checked_mul(&product, &count).ok_or(RaceError::Overflow)?;
+    }
+    Ok(product)
+}
+
+#[cfg(test)]
+fn race(duration: &str, record: &str) -> Race {
+    Race {
+        duration: duration.parse().unwrap(),
+        record: record.parse().unwrap(),
+    }
 }
 
 #[test]
 fn test_winning_speeds() {
     let races = vec![
-        Race{ duration: 7, record: 9 },
-        Race{ duration: 15, record: 40 },
-        Race{ duration: 30, record: 200 },
+        race("7", "9"),
+        race("15", "40"),
+        race("30", "200"),
     ];
 
-    assert_eq!(288, part1(races));
+    assert_eq!(Ok(n(288)), part1(races));
 
     let races = vec![
-        Race{ duration: 71530, record: 940200 },
+        race("71530", "940200"),
     ];
 
-    assert_eq!(71503, part1(races));
-}
\ No newline at end of file
+    assert_eq!(Ok(n(71503)), part1(races));
+}
+
+#[test]
+fn test_closed_form_matches_bruteforce() {
+    for duration in 0..80u32 {
+        for record in 0..duration * duration / 4 + 3 {
+            let race = Race { duration: n(duration), record: n(record) };
+            assert_eq!(winning_speeds_count(&race), winning_speeds_count_bruteforce(&race), "{} {}", duration, record);
+        }
+    }
+
+    let race = race("40817772", "219101213651089");
+    assert_eq!(winning_speeds_count(&race), winning_speeds_count_bruteforce(&race));
+}
+
+#[test]
+fn test_large_races() {
+    // duration² overflows u128 in all of these
+    let count = |duration, record| winning_speeds_count(&race(duration, record)).to_string();
+    assert_eq!(count("1000000000000000000000000000007", "100000000000000000000000000000000000000"), "999999999999999999999800000006");
+    assert_eq!(count("123456789012345678901234567890", "3810394796939796278353909350123456789"), "123456789012345678901172839493");
+    assert_eq!(
+        count("340282366920938463463374607431768211455", "340282366920938463463374607431768211455"),
+        "340282366920938463463374607431768211452",
+    );
+}
+
+#[cfg(feature = "bigint")]
+#[test]
+fn test_bigint_races() {
+    let count = |duration, record| winning_speeds_count(&race(duration, record)).to_string();
+    assert_eq!(count("1000000000000000000000000000007", "100000000000000000000000000000000000000000000000000000000000"), "774596669241483377035853079966");
+    assert_eq!(count("100000000000000000000000000000000000000", "2500000000000000000000000000000000000000000000000000000000000000000000000000"), "0");
+
+    let races = vec![race("1000000000000000000000", "0"), race("1000000000000000000000", "0")];
+    assert_eq!(part1(races).unwrap().to_string(), "999999999999999999998000000000000000000001");
+}
+
+#[test]
+fn test_degenerate_races() {
+    assert_eq!(race("0", "0").outcome(), Outcome::ZeroDuration);
+    assert_eq!(race("0", "5").outcome(), Outcome::ZeroDuration);
+    // 1 ms can only be spent holding or moving, never both
+    assert_eq!(race("1", "0").outcome(), Outcome::Unbeatable);
+    assert_eq!(race("30", "225").outcome(), Outcome::Unbeatable);
+    assert_eq!(race("30", "224").outcome(), Outcome::Count(n(1)));
+    assert_eq!(race("2", "0").outcome(), Outcome::Count(n(1)));
+
+    let error = part1(vec![race("7", "9"), race("0", "0"), race("30", "225")]).unwrap_err();
+    assert_eq!(error, RaceError::ZeroDuration { race: 2 });
+    assert_eq!(error.to_string(), "race 2 lasts 0 ms, so it can not be won");
+
+    let error = part1(vec![race("7", "9"), race("15", "40"), race("30", "225")]).unwrap_err();
+    assert_eq!(error, RaceError::Unbeatable { race: 3, duration: n(30), record: n(225) });
+    assert_eq!(error.to_string(), "race 3 can not beat its record of 225 mm in 30 ms");
+
+    assert_eq!(part1(vec![]), Ok(n(1)));
+}
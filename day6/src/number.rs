@@ -0,0 +1,46 @@
+use std::str::FromStr;
+
+// race values are u128 by default, or arbitrary precision integers with the
+// `bigint` feature. Everything else works on `Num` and the helpers below, so
+// the count is computed in whichever precision the races were read in
+#[cfg(not(feature = "bigint"))]
+pub type Num = u128;
+#[cfg(feature = "bigint")]
+pub type Num = num_bigint::BigUint;
+
+pub fn n(value: u32) -> Num {
+    Num::from(value)
+}
+
+#[cfg(not(feature = "bigint"))]
+pub fn isqrt(value: &Num) -> Num {
+    value.isqrt()
+}
+
+#[cfg(feature = "bigint")]
+pub fn isqrt(value: &Num) -> Num {
+    value.sqrt()
+}
+
+// None when the product does not fit, which never happens with bigint
+#[cfg(not(feature = "bigint"))]
+pub fn checked_mul(a: &Num, b: &Num) -> Option<Num> {
+    a.checked_mul(*b)
+}
+
+#[cfg(feature = "bigint")]
+pub fn checked_mul(a: &Num, b: &Num) -> Option<Num> {
+    Some(a * b)
+}
+
+// whether parsing failed because the digits do not fit, which never happens
+// with bigint
+#[cfg(not(feature = "bigint"))]
+pub fn overflowed(e: &<Num as FromStr>::Err) -> bool {
+    *e.kind() == std::num::IntErrorKind::PosOverflow
+}
+
+#[cfg(feature = "bigint")]
+pub fn overflowed(_: &<Num as FromStr>::Err) -> bool {
+    false
+}
@@ -0,0 +1,179 @@
+use std::fmt::{Display, Formatter};
+use std::{fs, io};
+use std::str::FromStr;
+use crate::number::{overflowed, Num};
+#[cfg(test)]
+use crate::race;
+use crate::Race;
+
+#[derive(Debug)]
+pub enum ParseError {
+    Io(io::Error),
+    MissingLine(&'static str),
+    UnknownHeader { line: usize, expected: &'static str, found: String },
+    NotANumber { line: usize, token: String },
+    TooLarge { line: usize, token: String },
+    ColumnMismatch { times: usize, distances: usize },
+    NoRaces,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::Io(e) => write!(f, "error reading races: {}", e),
+            ParseError::MissingLine(header) => write!(f, "missing the `{}` line", header),
+            ParseError::UnknownHeader { line, expected, found } => {
+                write!(f, "line {}: expected `{}`, found `{}`", line, expected, found)
+            }
+            ParseError::NotANumber { line, token } => write!(f, "line {}: `{}` is not a number", line, token),
+            ParseError::TooLarge { line, token } => {
+                write!(f, "line {}: `{}` does not fit in a u128, build with --features bigint", line, token)
+            }
+            ParseError::ColumnMismatch { times, distances } => {
+                write!(f, "{} times but {} distances, expected one of each per race", times, distances)
+            }
+            ParseError::NoRaces => write!(f, "no races, expected at least one time and distance"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ParseError {
+    fn from(e: io::Error) -> Self {
+        ParseError::Io(e)
+    }
+}
+
+// the words following `header` on the given line, with the line number
+fn columns<'a>(line: Option<(usize, &'a str)>, header: &'static str) -> Result<(usize, Vec<&'a str>), ParseError> {
+    let (number, line) = line.ok_or(ParseError::MissingLine(header))?;
+    let rest = line.strip_prefix(header).ok_or_else(|| ParseError::UnknownHeader {
+        line: number,
+        expected: header,
+        found: line.split_whitespace().next().unwrap_or("").to_string(),
+    })?;
+    Ok((number, rest.split_whitespace().collect()))
+}
+
+// kerned, the spaces between the columns are bad kerning and their digits
+// make up a single number
+fn numbers(line: usize, columns: &[&str], kerned: bool) -> Result<Vec<Num>, ParseError> {
+    let not_a_number = |token: &str| ParseError::NotANumber {
+        line,
+        token: token.to_string(),
+    };
+    let number = |token: &str| {
+        Num::from_str(token).map_err(|e| {
+            if overflowed(&e) {
+                ParseError::TooLarge {
+                    line,
+                    token: token.to_string(),
+                }
+            } else {
+                not_a_number(token)
+            }
+        })
+    };
+    if let Some(token) = columns.iter().find(|t| !t.bytes().all(|b| b.is_ascii_digit())) {
+        return Err(not_a_number(token));
+    }
+    if kerned {
+        return Ok(vec![number(&columns.concat())?]);
+    }
+    columns.iter().map(|t| number(t)).collect()
+}
+
+pub fn parse_str(contents: &str, kerned: bool) -> Result<Vec<Race>, ParseError> {
+    let mut lines = contents.lines().enumerate().map(|(i, l)| (i + 1, l)).filter(|(_, l)| !l.trim().is_empty());
+    let (time_line, times) = columns(lines.next(), "Time:")?;
+    let (distance_line, distances) = columns(lines.next(), "Distance:")?;
+    // checked before kerning, which would hide a missing column
+    if times.len() != distances.len() {
+        return Err(ParseError::ColumnMismatch {
+            times: times.len(),
+            distances: distances.len(),
+        });
+    }
+    if times.is_empty() {
+        return Err(ParseError::NoRaces);
+    }
+    let times = numbers(time_line, &times, kerned)?;
+    let distances = numbers(distance_line, &distances, kerned)?;
+
+    Ok(times
+        .into_iter()
+        .zip(distances)
+        .map(|(duration, record)| Race { duration, record })
+        .collect())
+}
+
+// reads a whole file, or stdin for `-`, so that both parts can be parsed
+// from the same input
+pub fn read(filename: &str) -> Result<String, ParseError> {
+    Ok(match filename {
+        "-" => io::read_to_string(io::stdin())?,
+        _ => fs::read_to_string(filename)?,
+    })
+}
+
+#[test]
+fn test_parse() {
+    let contents = fs::read_to_string("./test-input.txt").unwrap();
+    assert_eq!(
+        parse_str(&contents, false).unwrap(),
+        vec![race("7", "9"), race("15", "40"), race("30", "200")],
+    );
+    assert_eq!(parse_str(&contents, true).unwrap(), vec![race("71530", "940200")]);
+    assert_eq!(read("./test-input.txt").unwrap(), contents);
+    assert_eq!(parse_str("\r\nTime: 7\r\n\r\nDistance: 9\r\n", false).unwrap(), vec![race("7", "9")]);
+}
+
+#[test]
+fn test_parse_errors() {
+    let error = parse_str("Time: 7 15\nDistance: 9\n", false).unwrap_err();
+    assert!(matches!(error, ParseError::ColumnMismatch { times: 2, distances: 1 }));
+    assert_eq!(error.to_string(), "2 times but 1 distances, expected one of each per race");
+    let error = parse_str("Time: 7 15\nDistance: 9\n", true).unwrap_err();
+    assert!(matches!(error, ParseError::ColumnMismatch { times: 2, distances: 1 }));
+
+    let error = parse_str("Time: 7 1S\nDistance: 9 40\n", false).unwrap_err();
+    assert!(matches!(error, ParseError::NotANumber { line: 1, ref token } if token == "1S"));
+    assert_eq!(error.to_string(), "line 1: `1S` is not a number");
+
+    let error = parse_str("Time: 7 15\nDistance: 9 -40\n", true).unwrap_err();
+    assert!(matches!(error, ParseError::NotANumber { line: 2, ref token } if token == "-40"));
+
+    // the kerned distance is past u128, and only fits with bigint
+    let kerned = parse_str("Time: 7 15\nDistance: 99999999999999999999 99999999999999999999\n", true);
+    #[cfg(not(feature = "bigint"))]
+    {
+        let error = kerned.unwrap_err();
+        assert!(matches!(error, ParseError::TooLarge { line: 2, ref token } if token.len() == 40));
+        assert!(error.to_string().ends_with("does not fit in a u128, build with --features bigint"));
+    }
+    #[cfg(feature = "bigint")]
+    assert_eq!(kerned.unwrap()[0].record.to_string(), "9".repeat(40));
+
+    let error = parse_str("Time: 7\nRecord: 9\n", false).unwrap_err();
+    assert!(matches!(error, ParseError::UnknownHeader { line: 2, expected: "Distance:", ref found } if found == "Record:"));
+
+    for kerned in [false, true] {
+        let error = parse_str("Time:\nDistance:\n", kerned).unwrap_err();
+        assert!(matches!(error, ParseError::NoRaces));
+        assert_eq!(error.to_string(), "no races, expected at least one time and distance");
+    }
+
+    let error = parse_str("Time: 7\n", false).unwrap_err();
+    assert!(matches!(error, ParseError::MissingLine("Distance:")));
+
+    let error = read("./missing-input.txt").unwrap_err();
+    assert!(matches!(error, ParseError::Io(_)));
+}
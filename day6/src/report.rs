@@ -0,0 +1,186 @@
+use std::fmt::{Display, Formatter};
+use std::io::{self, Write};
+use std::str::FromStr;
+use crate::number::{checked_mul, n, Num};
+use crate::{first_winning_hold, Race, RaceError};
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Format {
+    Json,
+    Table,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct UnknownFormat(pub String);
+
+impl Display for UnknownFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown report format `{}`, expected json or table", self.0)
+    }
+}
+
+impl std::error::Error for UnknownFormat {}
+
+impl FromStr for Format {
+    type Err = UnknownFormat;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Format::Json),
+            "table" => Ok(Format::Table),
+            _ => Err(UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct RaceReport {
+    pub duration: Num,
+    pub record: Num,
+    // first and last winning hold times, both included
+    pub window: Option<(Num, Num)>,
+    pub best_hold: Num,
+    pub best_distance: Num,
+    // how far the best hold time goes past the record, None when it does not
+    pub margin: Option<Num>,
+}
+
+impl RaceReport {
+    #[allow(clippy::op_ref)]
+    pub fn count(&self) -> Num {
+        self.window.as_ref().map_or(n(0), |(first, last)| last - first + n(1))
+    }
+}
+
+// the distance is highest when holding for half the race, rounded either way
+#[allow(clippy::clone_on_copy, clippy::op_ref)]
+pub fn report(race: &Race) -> Result<RaceReport, RaceError> {
+    let best_hold = &race.duration / n(2);
+    let best_distance = checked_mul(&best_hold, &(&race.duration - &best_hold)).ok_or(RaceError::Overflow)?;
+    let margin = (best_distance > race.record).then(|| &best_distance - &race.record);
+    Ok(RaceReport {
+        duration: race.duration.clone(),
+        record: race.record.clone(),
+        window: first_winning_hold(race).map(|first| {
+            let last = &race.duration - &first;
+            (first, last)
+        }),
+        best_hold,
+        best_distance,
+        margin,
+    })
+}
+
+pub fn write_report(out: &mut impl Write, reports: &[RaceReport], format: Format) -> io::Result<()> {
+    match format {
+        Format::Json => write_json(out, reports),
+        Format::Table => write_table(out, reports),
+    }
+}
+
+fn or_null(value: &Option<Num>) -> String {
+    value.as_ref().map_or("null".to_string(), |v| v.to_string())
+}
+
+fn write_json(out: &mut impl Write, reports: &[RaceReport]) -> io::Result<()> {
+    writeln!(out, "[")?;
+    for (i, r) in reports.iter().enumerate() {
+        let window = r.window.as_ref().map_or("null".to_string(), |(first, last)| format!("[{}, {}]", first, last));
+        let separator = if i + 1 < reports.len() { "," } else { "" };
+        writeln!(
+            out,
+            "  {{\"race\": {}, \"duration\": {}, \"record\": {}, \"window\": {}, \"count\": {}, \"best_hold\": {}, \"best_distance\": {}, \"margin\": {}}}{}",
+            i + 1, r.duration, r.record, window, r.count(), r.best_hold, r.best_distance, or_null(&r.margin), separator,
+        )?;
+    }
+    writeln!(out, "]")
+}
+
+fn write_table(out: &mut impl Write, reports: &[RaceReport]) -> io::Result<()> {
+    let header = ["race", "duration", "record", "first", "last", "count", "best hold", "best distance", "margin"];
+    let mut rows = vec![header.map(String::from).to_vec()];
+    for (i, r) in reports.iter().enumerate() {
+        let (first, last) = r.window.as_ref().map_or(("-".to_string(), "-".to_string()), |(f, l)| (f.to_string(), l.to_string()));
+        rows.push(vec![
+            (i + 1).to_string(),
+            r.duration.to_string(),
+            r.record.to_string(),
+            first,
+            last,
+            r.count().to_string(),
+            r.best_hold.to_string(),
+            r.best_distance.to_string(),
+            r.margin.as_ref().map_or("-".to_string(), |m| m.to_string()),
+        ]);
+    }
+
+    let mut widths = vec![0; header.len()];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    for row in &rows {
+        let cells: Vec<String> = row.iter().zip(&widths).map(|(c, w)| format!("{:<w$}", c, w = w)).collect();
+        writeln!(out, "{}", cells.join("  ").trim_end())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+fn sample(format: Format) -> String {
+    let races = crate::parser::parse_str(&std::fs::read_to_string("./test-input.txt").unwrap(), false).unwrap();
+    let reports: Vec<RaceReport> = races.iter().map(|r| report(r).unwrap()).collect();
+    let mut out = Vec::new();
+    write_report(&mut out, &reports, format).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn test_report() {
+    let r = report(&crate::race("7", "9")).unwrap();
+    assert_eq!(r.window, Some((n(2), n(5))));
+    assert_eq!(r.count(), n(4));
+    assert_eq!((r.best_hold, r.best_distance, r.margin), (n(3), n(12), Some(n(3))));
+
+    // odd or even, the window is symmetric around the best hold time
+    let r = report(&crate::race("30", "200")).unwrap();
+    assert_eq!(r.window, Some((n(11), n(19))));
+    assert_eq!((r.best_hold, r.best_distance, r.margin), (n(15), n(225), Some(n(25))));
+
+    let r = report(&crate::race("30", "225")).unwrap();
+    assert_eq!((r.count(), r.window, r.margin), (n(0), None, None));
+
+    let r = report(&crate::race("0", "0")).unwrap();
+    assert_eq!((r.window, r.best_hold, r.best_distance, r.margin), (None, n(0), n(0), None));
+}
+
+#[cfg(not(feature = "bigint"))]
+#[test]
+fn test_report_overflow() {
+    let race = crate::race("340282366920938463463374607431768211455", "0");
+    assert_eq!(report(&race), Err(RaceError::Overflow));
+}
+
+#[test]
+fn test_report_json() {
+    assert_eq!(
+        sample(Format::Json),
+        "[\n  \
+         {\"race\": 1, \"duration\": 7, \"record\": 9, \"window\": [2, 5], \"count\": 4, \"best_hold\": 3, \"best_distance\": 12, \"margin\": 3},\n  \
+         {\"race\": 2, \"duration\": 15, \"record\": 40, \"window\": [4, 11], \"count\": 8, \"best_hold\": 7, \"best_distance\": 56, \"margin\": 16},\n  \
+         {\"race\": 3, \"duration\": 30, \"record\": 200, \"window\": [11, 19], \"count\": 9, \"best_hold\": 15, \"best_distance\": 225, \"margin\": 25}\n\
+         ]\n",
+    );
+}
+
+#[test]
+fn test_report_table() {
+    let table = sample(Format::Table);
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines[0], "race  duration  record  first  last  count  best hold  best distance  margin");
+    assert_eq!(lines[1], "1     7         9       2      5     4      3          12             3");
+    assert_eq!(lines[3], "3     30        200     11     19    9      15         225            25");
+    assert_eq!("json".parse(), Ok(Format::Json));
+    assert_eq!("csv".parse::<Format>(), Err(UnknownFormat("csv".to_string())));
+}